@@ -0,0 +1,168 @@
+use crate::SearchItem;
+
+pub const DEFAULT_ALBUM_TEMPLATE: &str = "<playlist>/<nn> - <title>";
+
+// the playlistItems endpoint and yt-dlp keep removed videos in the list under these titles
+const UNAVAILABLE_TITLES: [&str; 4] = [
+    "Deleted video",
+    "Private video",
+    "[Deleted video]",
+    "[Private video]",
+];
+
+// one video of a playlist in playlist order, position starts at 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlbumEntry {
+    pub position: usize,
+    pub video_id: String,
+    pub title: String,
+    pub channel: String,
+    // biggest first, for the cover art
+    pub thumbnails: Vec<String>,
+}
+
+// a whole playlist, fetched page by page before anything gets queued
+#[derive(Debug, Clone, PartialEq)]
+pub struct Album {
+    pub playlist_id: String,
+    pub title: String,
+    // the channel that owns the playlist
+    pub artist: String,
+    pub entries: Vec<AlbumEntry>,
+    // all positions, also the deleted and private videos that are not in entries
+    pub track_count: usize,
+}
+
+impl Album {
+    pub fn new(playlist_id: String, title: String, artist: String, items: Vec<SearchItem>) -> Self {
+        let track_count = items.len();
+        let entries = items
+            .into_iter()
+            .enumerate()
+            .filter(|(_, item)| !UNAVAILABLE_TITLES.contains(&item.snippet.title.as_str()))
+            .filter_map(|(index, item)| {
+                Some(AlbumEntry {
+                    position: index + 1,
+                    video_id: item.id.video_id?,
+                    thumbnails: item.snippet.thumbnails.best_urls(),
+                    title: item.snippet.title,
+                    channel: item.snippet.channel_title,
+                })
+            })
+            .collect();
+        Self {
+            playlist_id,
+            title,
+            artist,
+            entries,
+            track_count,
+        }
+    }
+
+    pub fn track(&self, entry: &AlbumEntry, template: &str) -> AlbumTrack {
+        AlbumTrack {
+            album: self.title.clone(),
+            album_artist: self.artist.clone(),
+            track: entry.position,
+            track_count: self.track_count,
+            file_name: render_template(template, self, entry),
+        }
+    }
+}
+
+// the album tags and file name of one queued playlist entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlbumTrack {
+    pub album: String,
+    pub album_artist: String,
+    pub track: usize,
+    pub track_count: usize,
+    // relative to the download folder, without extension and already escaped for -o
+    pub file_name: String,
+}
+
+// fills <playlist>, <artist>, <nn> and <title>, a / in the template starts a sub folder.
+// The values can not add folders of their own and a % in them is no yt-dlp field
+pub fn render_template(template: &str, album: &Album, entry: &AlbumEntry) -> String {
+    let width = album.track_count.to_string().len().max(2);
+    let template = if template.trim().is_empty() {
+        DEFAULT_ALBUM_TEMPLATE
+    } else {
+        template.trim()
+    };
+    template
+        .split(['/', '\\'])
+        .map(|part| {
+            part.replace("%", "%%")
+                .replace("<playlist>", &file_name_part(&album.title))
+                .replace("<artist>", &file_name_part(&entry.channel))
+                .replace("<nn>", &format!("{:0width$}", entry.position))
+                .replace("<title>", &file_name_part(&entry.title))
+        })
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// characters windows refuses in file names become _, dots at the end vanish on windows
+fn file_name_part(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').replace('%', "%%");
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn album(track_count: usize) -> Album {
+        Album {
+            playlist_id: "PL123".to_string(),
+            title: "Best of 100% Rock: Vol. 2".to_string(),
+            artist: "Rock Channel".to_string(),
+            entries: Vec::new(),
+            track_count,
+        }
+    }
+
+    fn entry(position: usize, title: &str) -> AlbumEntry {
+        AlbumEntry {
+            position,
+            video_id: "abc123def45".to_string(),
+            title: title.to_string(),
+            channel: "Some Band".to_string(),
+            thumbnails: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn template_numbers_and_cleans_the_names() {
+        assert_eq!(
+            render_template(
+                DEFAULT_ALBUM_TEMPLATE,
+                &album(12),
+                &entry(3, "AC/DC - T.N.T.")
+            ),
+            "Best of 100%% Rock_ Vol. 2/03 - AC_DC - T.N.T"
+        );
+        assert_eq!(
+            render_template("<artist>/<nn> <title>", &album(120), &entry(7, "Song")),
+            "Some Band/007 Song"
+        );
+        assert_eq!(
+            render_template("  ", &album(2), &entry(1, "?")),
+            "Best of 100%% Rock_ Vol. 2/01 - _"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use eframe::egui::{self, Color32};
+use image::{DynamicImage, RgbImage, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::ffmpeg;
+use crate::formats::AudioFormat;
+use crate::tags::has_extension;
+
+// a pixel darker than this counts as part of a black bar
+const BAR_LUMA: u8 = 40;
+// the share of dark pixels that makes a whole row or column a bar, jpeg noise leaves a few bright ones
+const BAR_SHARE: f32 = 0.98;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CoverOptions {
+    pub enabled: bool,
+    // edge length of the square in pixels
+    pub size: u32,
+    // also keep the square jpg, cover.jpg in an album folder, named after the file otherwise
+    pub save_file: bool,
+}
+
+impl Default for CoverOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 600,
+            save_file: false,
+        }
+    }
+}
+
+impl CoverOptions {
+    pub const SIZES: [u32; 5] = [300, 500, 600, 800, 1200];
+
+    // format is the audio format of the settings, not every container takes a cover
+    pub fn show(&mut self, ui: &mut egui::Ui, format: AudioFormat) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.enabled, "square cover art");
+            if !self.enabled {
+                return;
+            }
+            ui.add_space(20.0);
+            egui::ComboBox::from_id_salt("cover_size")
+                .selected_text(format!("{0}×{0}", self.size))
+                .show_ui(ui, |ui| {
+                    for size in CoverOptions::SIZES {
+                        ui.selectable_value(&mut self.size, size, format!("{size}×{size}"));
+                    }
+                });
+            ui.add_space(20.0);
+            ui.checkbox(&mut self.save_file, "save cover as jpg");
+        });
+        if !self.enabled {
+            return;
+        }
+        let note = match format {
+            AudioFormat::Opus | AudioFormat::Vorbis => {
+                "opus and vorbis files keep the thumbnail yt-dlp embeds, only the saved jpg is square"
+            }
+            AudioFormat::Wav => "wav files take no cover, only the saved jpg is made",
+            AudioFormat::Mp3 | AudioFormat::M4a | AudioFormat::Flac => return,
+        };
+        ui.colored_label(Color32::GRAY, note);
+    }
+}
+
+// what a job needs to make its cover, the urls are the thumbnails from big to small
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverArt {
+    pub urls: Vec<String>,
+    pub size: u32,
+    pub save_file: bool,
+    // the file has a folder of its own, so the cover can be cover.jpg
+    pub album_folder: bool,
+}
+
+impl CoverArt {
+    // None when switched off or the item has no thumbnails
+    pub fn new(options: CoverOptions, urls: Vec<String>) -> Option<Self> {
+        (options.enabled && !urls.is_empty()).then_some(Self {
+            urls,
+            size: options.size,
+            save_file: options.save_file,
+            album_folder: false,
+        })
+    }
+}
+
+// fetches, squares and embeds the cover as front cover of the finished file
+pub async fn add_cover(ffmpeg: &Path, path: &Path, cover: &CoverArt) -> Result<(), AppError> {
+    if !can_embed(path) && !cover.save_file {
+        return Ok(());
+    }
+    let original = fetch_best(&cover.urls).await?;
+    let size = cover.size;
+    let jpeg = tokio::task::spawn_blocking(move || square_cover(&original, size))
+        .await
+        .map_err(|error| AppError::Image(error.to_string()))??;
+    let save_as = cover
+        .save_file
+        .then(|| saved_cover_path(path, cover.album_folder));
+    embed_cover(ffmpeg, path, &jpeg, save_as).await
+}
+
+// only these containers take an attached picture from ffmpeg,
+// opus, vorbis and wav keep the thumbnail yt-dlp embedded
+const EMBED_EXTENSIONS: [&str; 3] = ["mp3", "m4a", "flac"];
+
+pub fn can_embed(path: &Path) -> bool {
+    has_extension(path, &EMBED_EXTENSIONS)
+}
+
+// every track of a download folder would overwrite one cover.jpg, so only an album gets it
+fn saved_cover_path(path: &Path, album_folder: bool) -> PathBuf {
+    if album_folder {
+        path.with_file_name("cover.jpg")
+    } else {
+        path.with_extension("jpg")
+    }
+}
+
+// replaces the front cover with a finished jpeg, save_as keeps the jpeg there
+pub async fn embed_cover(
+    ffmpeg: &Path,
+    path: &Path,
+    jpeg: &[u8],
+    save_as: Option<PathBuf>,
+) -> Result<(), AppError> {
+    let cover_path = save_as
+        .clone()
+        .unwrap_or_else(|| path.with_extension("cover.jpg"));
+    std::fs::write(&cover_path, jpeg)?;
+    let result = if can_embed(path) {
+        ffmpeg::rewrite(ffmpeg, path, embed_args(path, &cover_path)).await
+    } else {
+        Ok(())
+    };
+    if save_as.is_none() {
+        let _ = std::fs::remove_file(&cover_path);
+    }
+    result
+}
+
+// the embedded picture as it is, None if there is none
+pub async fn read_cover(ffmpeg: &Path, path: &Path) -> Option<Vec<u8>> {
+    let args = [
+        "-v".to_string(),
+        "error".to_string(),
+        "-i".to_string(),
+        path.to_string_lossy().into_owned(),
+        "-an".to_string(),
+        "-c:v".to_string(),
+        "copy".to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-f".to_string(),
+        "image2pipe".to_string(),
+        "-".to_string(),
+    ];
+    ffmpeg::run(ffmpeg, &args)
+        .await
+        .ok()
+        .filter(|bytes| !bytes.is_empty())
+}
+
+// the old cover stream is left out, the new one becomes the front cover
+fn embed_args(path: &Path, cover_path: &Path) -> Vec<String> {
+    let mut args = vec!["-i".to_string(), cover_path.to_string_lossy().into_owned()];
+    args.extend(
+        [
+            "-map",
+            "0:a",
+            "-map",
+            "1:v",
+            "-c",
+            "copy",
+            "-disposition:v:0",
+            "attached_pic",
+            "-metadata:s:v:0",
+            "title=Album cover",
+            "-metadata:s:v:0",
+            "comment=Cover (front)",
+        ]
+        .map(String::from),
+    );
+    if has_extension(path, &["mp3"]) {
+        args.extend(["-id3v2_version", "3"].map(String::from));
+    }
+    args
+}
+
+// maxresdefault does not exist for every video, so the next one is tried
+async fn fetch_best(urls: &[String]) -> Result<Vec<u8>, AppError> {
+    let mut last_error = AppError::NotFound("thumbnail".to_string());
+    for url in urls {
+        let response = match reqwest::get(url).await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response,
+            Err(error) => {
+                last_error = error.into();
+                continue;
+            }
+        };
+        match response.bytes().await {
+            Ok(bytes) => return Ok(bytes.to_vec()),
+            Err(error) => last_error = error.into(),
+        }
+    }
+    Err(last_error)
+}
+
+// removes the letterbox, crops the middle square and scales it to size, as jpeg
+pub fn square_cover(original: &[u8], size: u32) -> Result<Vec<u8>, AppError> {
+    let image = image::load_from_memory(original)?;
+    let rgb = image.to_rgb8();
+    let (x, y, width, height) = content_bounds(&rgb);
+    let side = width.min(height);
+    let square = image.crop_imm(x + (width - side) / 2, y + (height - side) / 2, side, side);
+    let resized = square.resize_exact(size, size, FilterType::Lanczos3);
+    let mut jpeg = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(Cursor::new(&mut jpeg), 90);
+    DynamicImage::ImageRgb8(resized.to_rgb8()).write_with_encoder(encoder)?;
+    Ok(jpeg)
+}
+
+// the part of the image without black bars on any side, the whole image if that leaves too little
+fn content_bounds(image: &RgbImage) -> (u32, u32, u32, u32) {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return (0, 0, width, height);
+    }
+    let dark = |x: u32, y: u32| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        luma < BAR_LUMA as u32
+    };
+    let row_is_bar =
+        |y: u32| (0..width).filter(|x| dark(*x, y)).count() as f32 >= width as f32 * BAR_SHARE;
+    let column_is_bar =
+        |x: u32| (0..height).filter(|y| dark(x, *y)).count() as f32 >= height as f32 * BAR_SHARE;
+
+    let top = (0..height).find(|y| !row_is_bar(*y)).unwrap_or(0);
+    let bottom = (0..height)
+        .rev()
+        .find(|y| !row_is_bar(*y))
+        .unwrap_or(height - 1);
+    let left = (0..width).find(|x| !column_is_bar(*x)).unwrap_or(0);
+    let right = (0..width)
+        .rev()
+        .find(|x| !column_is_bar(*x))
+        .unwrap_or(width - 1);
+
+    let content_width = right.saturating_sub(left) + 1;
+    let content_height = bottom.saturating_sub(top) + 1;
+    // a mostly black picture is no letterbox
+    if content_width < width / 2 || content_height < height / 2 {
+        return (0, 0, width, height);
+    }
+    (left, top, content_width, content_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb};
+
+    #[test]
+    fn letterbox_is_cut_before_the_square() {
+        // 16:9 content with black bars above and below, like YouTube's 4:3 hqdefault
+        let mut original = RgbImage::from_pixel(160, 120, Rgb([0, 0, 0]));
+        for y in 15..105 {
+            for x in 0..160 {
+                let red = if x < 80 { 200 } else { 30 };
+                original.put_pixel(x, y, Rgb([red, 120, 60]));
+            }
+        }
+        // a bit of jpeg noise in the bar
+        original.put_pixel(3, 5, Rgb([90, 90, 90]));
+        assert_eq!(content_bounds(&original), (0, 15, 160, 90));
+
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(original)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let cover = image::load_from_memory(&square_cover(&png, 64).unwrap()).unwrap();
+        assert_eq!(cover.dimensions(), (64, 64));
+        // no black row left at the top or bottom
+        for (x, y) in [(32, 0), (32, 63)] {
+            let [r, g, b, _] = cover.get_pixel(x, y).0;
+            assert!(r as u32 + g as u32 + b as u32 > 100, "{x} {y}");
+        }
+    }
+
+    #[test]
+    fn only_albums_share_a_cover_jpg() {
+        let path = Path::new("/music/Mixtape/01 - Song.mp3");
+        assert_eq!(
+            saved_cover_path(path, true),
+            PathBuf::from("/music/Mixtape/cover.jpg")
+        );
+        assert_eq!(
+            saved_cover_path(path, false),
+            PathBuf::from("/music/Mixtape/01 - Song.jpg")
+        );
+    }
+
+    #[test]
+    fn dark_pictures_are_not_cropped() {
+        let mut original = RgbImage::from_pixel(100, 100, Rgb([5, 5, 5]));
+        original.put_pixel(50, 50, Rgb([255, 255, 255]));
+        assert_eq!(content_bounds(&original), (0, 0, 100, 100));
+    }
+}
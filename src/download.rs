@@ -0,0 +1,738 @@
+use eframe::egui::{self, Color32};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::oneshot;
+
+use crate::album::AlbumTrack;
+use crate::cover::{CoverArt, add_cover};
+use crate::error::AppError;
+use crate::events::{AppEvent, EventSender};
+use crate::formats::{AudioOptions, DownloadOptions};
+use crate::loudness::{self, LoudnessMode, LoudnessOptions, takes_replaygain};
+use crate::platform::output_template;
+use crate::progress::{DownloadPhase, DownloadProgress};
+use crate::tags::{TrackTags, write_tags};
+use crate::toolchain::{Tool, Toolchain};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Paused,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Paused => "paused",
+            JobStatus::Running => "downloading",
+            JobStatus::Finished => "done",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Finished | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+// what a result row shows, one per video id so it survives a new search
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DownloadState {
+    #[default]
+    Idle,
+    Queued(usize),
+    Paused(usize),
+    Downloading(usize, DownloadProgress),
+    PostProcessing(usize, DownloadProgress),
+    Done,
+    Failed(usize, AppError),
+    Cancelled,
+}
+
+impl DownloadState {
+    fn job_id(&self) -> Option<usize> {
+        match self {
+            DownloadState::Queued(job_id)
+            | DownloadState::Paused(job_id)
+            | DownloadState::Downloading(job_id, _)
+            | DownloadState::PostProcessing(job_id, _)
+            | DownloadState::Failed(job_id, _) => Some(*job_id),
+            DownloadState::Idle | DownloadState::Done | DownloadState::Cancelled => None,
+        }
+    }
+}
+
+pub struct DownloadJob {
+    pub id: usize,
+    pub video_id: String,
+    pub title: String,
+    pub channel: String,
+    pub url: String,
+    pub download_path: String,
+    pub options: DownloadOptions,
+    pub status: JobStatus,
+    pub progress: Option<DownloadProgress>,
+    pub error: Option<AppError>,
+    // set when the job is one entry of a playlist download
+    pub album: Option<AlbumTrack>,
+    // written into the file once yt-dlp is done
+    pub tags: TrackTags,
+    // None keeps the thumbnail yt-dlp embeds
+    pub cover: Option<CoverArt>,
+    // None leaves the volume as uploaded
+    pub loudness: Option<LoudnessOptions>,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+impl DownloadJob {
+    pub fn new(
+        video_id: String,
+        title: String,
+        channel: String,
+        url: String,
+        download_path: String,
+        options: DownloadOptions,
+    ) -> Self {
+        let tags = TrackTags::parse(&title, &channel);
+        Self {
+            id: 0,
+            video_id,
+            title,
+            channel,
+            url,
+            download_path,
+            options,
+            status: JobStatus::Queued,
+            progress: None,
+            error: None,
+            album: None,
+            tags,
+            cover: None,
+            loudness: None,
+            cancel: None,
+        }
+    }
+
+    pub fn in_album(mut self, track: AlbumTrack) -> Self {
+        self.tags = self.tags.with_album(&track);
+        self.album = Some(track);
+        self
+    }
+
+    // only audio downloads get a square cover, a video keeps its frame
+    pub fn with_cover(mut self, cover: Option<CoverArt>) -> Self {
+        if matches!(self.options, DownloadOptions::Audio(_)) {
+            // a template with / puts every album into its own folder
+            let album_folder = self
+                .album
+                .as_ref()
+                .is_some_and(|track| track.file_name.contains('/'));
+            self.cover = cover.map(|cover| CoverArt {
+                album_folder,
+                ..cover
+            });
+        }
+        self
+    }
+
+    // the loudness of a video stays as it is, like its cover. ReplayGain is skipped for
+    // formats without a place for the tags, a single row can still be m4a or wav
+    pub fn with_loudness(mut self, loudness: LoudnessOptions) -> Self {
+        let DownloadOptions::Audio(audio) = &self.options else {
+            return self;
+        };
+        let applies = match loudness.mode {
+            LoudnessMode::Off => false,
+            LoudnessMode::Normalize => true,
+            LoudnessMode::ReplayGain => takes_replaygain(audio.format),
+        };
+        if applies {
+            self.loudness = Some(loudness);
+        }
+        self
+    }
+}
+
+// every Download click lands here, the manager decides when a yt-dlp child gets started
+#[derive(Default)]
+pub struct DownloadManager {
+    jobs: Vec<DownloadJob>,
+    next_id: usize,
+    states: HashMap<String, DownloadState>,
+}
+
+impl DownloadManager {
+    pub fn enqueue(&mut self, mut job: DownloadJob) -> usize {
+        self.next_id += 1;
+        job.id = self.next_id;
+        job.status = JobStatus::Queued;
+        self.states
+            .insert(job.video_id.clone(), DownloadState::Queued(job.id));
+        self.jobs.push(job);
+        self.next_id
+    }
+
+    pub fn state(&self, video_id: &str) -> &DownloadState {
+        const IDLE: &DownloadState = &DownloadState::Idle;
+        self.states.get(video_id).unwrap_or(IDLE)
+    }
+
+    // only the newest job of a video may change its state, an old one that reports late is ignored
+    fn set_state(&mut self, job_id: usize, state: DownloadState) {
+        let Some(job) = self.jobs.iter().find(|job| job.id == job_id) else {
+            return;
+        };
+        let current = self.states.entry(job.video_id.clone()).or_default();
+        if current
+            .job_id()
+            .is_none_or(|current_id| current_id == job_id)
+        {
+            *current = state;
+        }
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Running)
+            .count()
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.jobs.iter().filter(|job| !job.status.is_over()).count()
+    }
+
+    // starts queued jobs in queue order until max_parallel children are running,
+    // without a working yt-dlp everything stays queued
+    pub fn start_queued(
+        &mut self,
+        max_parallel: usize,
+        toolchain: &Toolchain,
+        events: &EventSender,
+    ) {
+        let Some(yt_dlp) = toolchain.path(Tool::YtDlp) else {
+            return;
+        };
+        let ffmpeg = toolchain.path(Tool::Ffmpeg).map(PathBuf::from);
+        let mut free_slots = max_parallel.max(1).saturating_sub(self.running_count());
+        for job in self.jobs.iter_mut() {
+            if free_slots == 0 {
+                break;
+            }
+            if job.status != JobStatus::Queued {
+                continue;
+            }
+            free_slots -= 1;
+
+            let (cancel_tx, cancel_rx) = oneshot::channel();
+            job.cancel = Some(cancel_tx);
+            job.status = JobStatus::Running;
+            // yt-dlp needs a moment until the first progress line
+            if let Some(state) = self.states.get_mut(&job.video_id)
+                && state.job_id() == Some(job.id)
+            {
+                *state = DownloadState::Downloading(job.id, DownloadProgress::default());
+            }
+
+            let events = events.clone();
+            let job_id = job.id;
+            let task = DownloadTask {
+                yt_dlp: yt_dlp.to_path_buf(),
+                ffmpeg: ffmpeg.clone(),
+                url: job.url.clone(),
+                download_path: job.download_path.clone(),
+                options: job.options,
+                album: job.album.clone(),
+                tags: job.tags.clone(),
+                cover: job.cover.clone(),
+                loudness: job.loudness,
+            };
+            tokio::spawn(async move {
+                let result = downlaod_from_dlp(&events, job_id, task, cancel_rx).await;
+                let event = match result {
+                    Ok(output_path) => AppEvent::Done(job_id, output_path),
+                    Err(error) => {
+                        eprintln!("download failed with: {error}");
+                        AppEvent::Failed(job_id, error)
+                    }
+                };
+                events.send(event);
+            });
+        }
+    }
+
+    // marks the job as over and hands it back
+    pub fn finish(&mut self, job_id: usize, status: JobStatus) -> Option<&DownloadJob> {
+        let job = self.jobs.iter_mut().find(|job| job.id == job_id)?;
+        job.cancel = None;
+        // a cancelled job still reports back once the child is gone
+        let state = if job.status == JobStatus::Cancelled {
+            None
+        } else {
+            job.status = status;
+            match status {
+                JobStatus::Finished => Some(DownloadState::Done),
+                JobStatus::Failed => job
+                    .error
+                    .clone()
+                    .map(|error| DownloadState::Failed(job_id, error)),
+                JobStatus::Cancelled => Some(DownloadState::Cancelled),
+                JobStatus::Queued | JobStatus::Paused | JobStatus::Running => None,
+            }
+        };
+        if let Some(state) = state {
+            self.set_state(job_id, state);
+        }
+        self.jobs.iter().find(|job| job.id == job_id)
+    }
+
+    pub fn fail(&mut self, job_id: usize, error: AppError) -> Option<&DownloadJob> {
+        let job = self.jobs.iter_mut().find(|job| job.id == job_id)?;
+        if job.status == JobStatus::Cancelled {
+            return None;
+        }
+        job.error = Some(error);
+        self.finish(job_id, JobStatus::Failed)
+    }
+
+    // a failed job goes back to the end of the queue
+    pub fn retry(&mut self, job_id: usize) {
+        let Some(position) = self
+            .jobs
+            .iter()
+            .position(|job| job.id == job_id && job.status == JobStatus::Failed)
+        else {
+            return;
+        };
+        let mut job = self.jobs.remove(position);
+        job.status = JobStatus::Queued;
+        job.progress = None;
+        job.error = None;
+        self.states
+            .insert(job.video_id.clone(), DownloadState::Queued(job_id));
+        self.jobs.push(job);
+    }
+
+    pub fn update_progress(&mut self, job_id: usize, progress: DownloadProgress) {
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == job_id) else {
+            return;
+        };
+        if job.status != JobStatus::Running {
+            return;
+        }
+        job.progress = Some(progress.clone());
+        let state = match progress.phase {
+            DownloadPhase::Downloading => DownloadState::Downloading(job_id, progress),
+            DownloadPhase::PostProcessing(_) | DownloadPhase::Finished => {
+                DownloadState::PostProcessing(job_id, progress)
+            }
+        };
+        self.set_state(job_id, state);
+    }
+
+    // queued jobs are dropped right away, running ones get their child killed
+    pub fn cancel(&mut self, job_id: usize) {
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == job_id) else {
+            return;
+        };
+        if job.status.is_over() {
+            return;
+        }
+        if let Some(cancel) = job.cancel.take() {
+            let _ = cancel.send(());
+        }
+        job.status = JobStatus::Cancelled;
+        self.set_state(job_id, DownloadState::Cancelled);
+    }
+
+    pub fn toggle_pause(&mut self, job_id: usize) {
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == job_id) else {
+            return;
+        };
+        let (status, state) = match job.status {
+            JobStatus::Queued => (JobStatus::Paused, DownloadState::Paused(job_id)),
+            JobStatus::Paused => (JobStatus::Queued, DownloadState::Queued(job_id)),
+            _ => return,
+        };
+        job.status = status;
+        self.set_state(job_id, state);
+    }
+
+    // moves a waiting job one place up (-1) or down (1) among the waiting jobs,
+    // running and finished ones keep their place
+    pub fn move_job(&mut self, job_id: usize, direction: isize) {
+        let is_waiting =
+            |job: &DownloadJob| matches!(job.status, JobStatus::Queued | JobStatus::Paused);
+        let Some(position) = self
+            .jobs
+            .iter()
+            .position(|job| job.id == job_id && is_waiting(job))
+        else {
+            return;
+        };
+        let target = if direction < 0 {
+            self.jobs[..position].iter().rposition(is_waiting)
+        } else {
+            self.jobs[position + 1..]
+                .iter()
+                .position(is_waiting)
+                .map(|offset| position + 1 + offset)
+        };
+        if let Some(target) = target {
+            self.jobs.swap(position, target);
+        }
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|job| !job.status.is_over());
+    }
+
+    // the queue panel
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let mut cancel = None;
+        let mut retry = None;
+        let mut pause = None;
+        let mut move_by = None;
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} running, {} waiting",
+                self.running_count(),
+                self.active_count() - self.running_count()
+            ));
+            if ui.button("clear finished").clicked() {
+                self.clear_finished();
+            }
+        });
+        ui.add_space(20.0);
+
+        if self.jobs.is_empty() {
+            ui.colored_label(Color32::GRAY, "nothing in the queue");
+        }
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for job in &self.jobs {
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label(&job.title);
+                            ui.colored_label(Color32::GRAY, job.options.label());
+                            match (&job.progress, job.status) {
+                                (_, JobStatus::Failed) => match &job.error {
+                                    Some(error) => error.show(ui),
+                                    None => {
+                                        ui.colored_label(Color32::RED, job.status.label());
+                                    }
+                                },
+                                (Some(progress), JobStatus::Running) => {
+                                    ui.add(
+                                        egui::ProgressBar::new(progress.percent)
+                                            .desired_width(300.0)
+                                            .show_percentage(),
+                                    );
+                                    ui.colored_label(Color32::GRAY, progress.status_text());
+                                }
+                                _ => {
+                                    ui.colored_label(Color32::GRAY, job.status.label());
+                                }
+                            }
+                        });
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                            if job.status == JobStatus::Failed && ui.button("retry").clicked() {
+                                retry = Some(job.id);
+                            }
+                            if job.status.is_over() {
+                                return;
+                            }
+                            if ui.button("✖").on_hover_text("cancel").clicked() {
+                                cancel = Some(job.id);
+                            }
+                            let pause_button = match job.status {
+                                JobStatus::Queued => Some(("⏸", "pause")),
+                                JobStatus::Paused => Some(("▶", "resume")),
+                                _ => None,
+                            };
+                            if let Some((icon, hover)) = pause_button
+                                && ui.button(icon).on_hover_text(hover).clicked()
+                            {
+                                pause = Some(job.id);
+                            }
+                            if job.status != JobStatus::Running {
+                                if ui.button("⏷").clicked() {
+                                    move_by = Some((job.id, 1));
+                                }
+                                if ui.button("⏶").clicked() {
+                                    move_by = Some((job.id, -1));
+                                }
+                            }
+                        });
+                    });
+                    ui.add(egui::Separator::default());
+                }
+            });
+
+        if let Some(job_id) = cancel {
+            self.cancel(job_id);
+        }
+        if let Some(job_id) = retry {
+            self.retry(job_id);
+        }
+        if let Some(job_id) = pause {
+            self.toggle_pause(job_id);
+        }
+        if let Some((job_id, direction)) = move_by {
+            self.move_job(job_id, direction);
+        }
+    }
+}
+
+// yt-dlp prints the final file path with this prefix once the file is in place
+const OUTPUT_PATH_PREFIX: &str = "output_path:";
+
+// everything one yt-dlp child needs, copied out of the job
+struct DownloadTask {
+    yt_dlp: PathBuf,
+    // None lets yt-dlp look for ffmpeg itself
+    ffmpeg: Option<PathBuf>,
+    url: String,
+    download_path: String,
+    options: DownloadOptions,
+    album: Option<AlbumTrack>,
+    tags: TrackTags,
+    cover: Option<CoverArt>,
+    loudness: Option<LoudnessOptions>,
+}
+
+// runs one yt-dlp child and reports its progress, a message on cancel kills it.
+// Returns the path of the finished file, None if cancelled or yt-dlp did not print it
+async fn downlaod_from_dlp(
+    events: &EventSender,
+    job_id: usize,
+    task: DownloadTask,
+    mut cancel: oneshot::Receiver<()>,
+) -> Result<Option<String>, AppError> {
+    let file_name = match &task.album {
+        Some(track) => track.file_name.as_str(),
+        None => "%(title)s",
+    };
+    let download_string = output_template(&task.download_path, file_name);
+
+    let print_output_path = format!("after_move:{OUTPUT_PATH_PREFIX}%(filepath)s");
+
+    let mut command = task.options.yt_dlp_args();
+    if let Some(ffmpeg) = &task.ffmpeg {
+        command.push("--ffmpeg-location".to_string());
+        command.push(ffmpeg.to_string_lossy().into_owned());
+    }
+    command.extend(
+        [
+            "-o",
+            &download_string,
+            "--add-metadata",
+            "--newline",
+            // --print makes yt-dlp quiet, --progress keeps the progress lines
+            "--no-simulate",
+            "--progress",
+            "--print",
+            &print_output_path,
+            "--progress-template",
+            "download:%(progress)j",
+            "--progress-template",
+            "postprocess:%(progress)j",
+            &task.url,
+        ]
+        .map(String::from),
+    );
+
+    let mut output = tokio::process::Command::new(&task.yt_dlp)
+        .args(&command)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|error| AppError::Spawn {
+            program: task.yt_dlp.to_string_lossy().into_owned(),
+            message: error.to_string(),
+        })?;
+
+    let stdout = output.stdout.take();
+    let stderr = output.stderr.take();
+    let mut output_path = None;
+    let mut stderr_text = String::new();
+    let read_stdout = async {
+        if let Some(stdout) = stdout {
+            let reader = tokio::io::BufReader::new(stdout);
+            let mut lines = reader.lines();
+
+            while let Some(line) = lines.next_line().await? {
+                if let Some(path) = line.strip_prefix(OUTPUT_PATH_PREFIX) {
+                    output_path = Some(path.trim().to_string());
+                } else if let Some(progress) = DownloadProgress::parse(&line) {
+                    events.progress(job_id, progress);
+                }
+            }
+        }
+        Ok::<(), std::io::Error>(())
+    };
+    // read at the same time, a full stderr pipe would block yt-dlp otherwise
+    let read_stderr = async {
+        if let Some(stderr) = stderr {
+            let reader = tokio::io::BufReader::new(stderr);
+            let mut lines = reader.lines();
+
+            while let Some(line) = lines.next_line().await? {
+                stderr_text.push_str(&line);
+                stderr_text.push('\n');
+            }
+        }
+        Ok::<(), std::io::Error>(())
+    };
+
+    tokio::select! {
+        (stdout_result, stderr_result) = async { tokio::join!(read_stdout, read_stderr) } => {
+            stdout_result?;
+            stderr_result?;
+        }
+        Ok(()) = &mut cancel => {
+            output.kill().await?;
+            return Ok(None);
+        }
+    }
+    let status = output.wait().await?;
+    if !status.success() {
+        return Err(AppError::yt_dlp(status.code(), &stderr_text));
+    }
+    // without ffmpeg the file keeps what --add-metadata wrote, the title parser is made
+    // for songs so a video keeps those tags as well
+    if let (Some(ffmpeg), Some(path), DownloadOptions::Audio(audio)) =
+        (&task.ffmpeg, &output_path, &task.options)
+    {
+        // dropping the steps on cancel kills the running ffmpeg
+        tokio::select! {
+            result = post_process(events, job_id, &task, ffmpeg, Path::new(path), audio) => result?,
+            Ok(()) = &mut cancel => return Ok(None),
+        }
+    }
+    Ok(output_path)
+}
+
+// the ffmpeg steps that follow a finished audio download
+async fn post_process(
+    events: &EventSender,
+    job_id: usize,
+    task: &DownloadTask,
+    ffmpeg: &Path,
+    path: &Path,
+    audio: &AudioOptions,
+) -> Result<(), AppError> {
+    events.progress(
+        job_id,
+        DownloadProgress {
+            phase: DownloadPhase::PostProcessing("WriteTags".to_string()),
+            percent: 1.0,
+            ..Default::default()
+        },
+    );
+    write_tags(ffmpeg, path, &task.tags).await?;
+    // yt-dlp already embedded its thumbnail, so the square cover is only an extra
+    if let Some(cover) = &task.cover
+        && let Err(error) = add_cover(ffmpeg, path, cover).await
+    {
+        eprintln!("cover art skipped for {}: {error}", path.display());
+    }
+    if let Some(loudness) = &task.loudness {
+        events.progress(
+            job_id,
+            DownloadProgress {
+                phase: DownloadPhase::PostProcessing("Loudness".to_string()),
+                percent: 1.0,
+                ..Default::default()
+            },
+        );
+        loudness::apply(ffmpeg, path, loudness, audio).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::AudioFormat;
+
+    fn job(video_id: &str) -> DownloadJob {
+        DownloadJob::new(
+            video_id.to_string(),
+            video_id.to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            DownloadOptions::Audio(Default::default()),
+        )
+    }
+
+    fn order(manager: &DownloadManager) -> Vec<&str> {
+        manager
+            .jobs
+            .iter()
+            .map(|job| job.video_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn moves_only_among_waiting_jobs() {
+        let mut manager = DownloadManager::default();
+        let ids: Vec<usize> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|video_id| manager.enqueue(job(video_id)))
+            .collect();
+        manager.jobs[1].status = JobStatus::Running;
+        manager.finish(ids[2], JobStatus::Finished);
+
+        // d jumps over the running and the finished job
+        manager.move_job(ids[3], -1);
+        assert_eq!(order(&manager), vec!["d", "b", "c", "a"]);
+        // nothing waits above d, nothing below a
+        manager.move_job(ids[3], -1);
+        manager.move_job(ids[0], 1);
+        assert_eq!(order(&manager), vec!["d", "b", "c", "a"]);
+        // a running job does not move
+        manager.move_job(ids[1], 1);
+        assert_eq!(order(&manager), vec!["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn pausing_shows_on_the_row() {
+        let mut manager = DownloadManager::default();
+        let id = manager.enqueue(job("a"));
+        manager.toggle_pause(id);
+        assert_eq!(manager.state("a"), &DownloadState::Paused(id));
+        manager.toggle_pause(id);
+        assert_eq!(manager.state("a"), &DownloadState::Queued(id));
+    }
+
+    #[test]
+    fn replaygain_only_where_tags_fit() {
+        let replaygain = LoudnessOptions {
+            mode: LoudnessMode::ReplayGain,
+            ..Default::default()
+        };
+        let with_format = |format| {
+            let mut job = job("a");
+            job.options = DownloadOptions::Audio(AudioOptions::for_format(format));
+            job.with_loudness(replaygain).loudness
+        };
+        assert_eq!(with_format(AudioFormat::Mp3), Some(replaygain));
+        assert_eq!(with_format(AudioFormat::M4a), None);
+        assert_eq!(with_format(AudioFormat::Wav), None);
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+// parses ISO 8601 durations like the YouTube API sends them (PT4M, PT45S, P1DT2H3M4S)
+// years and months have no fixed length, so those are rejected
+pub fn parse_iso8601_duration(value: &str) -> Option<Duration> {
+    let rest = value.strip_prefix('P')?;
+    let (date_part, time_part) = match rest.split_once('T') {
+        Some((date, time)) => {
+            if time.is_empty() {
+                return None;
+            }
+            (date, Some(time))
+        }
+        None => (rest, None),
+    };
+    if date_part.is_empty() && time_part.is_none() {
+        return None;
+    }
+
+    let mut seconds = sum_components(date_part, &[('W', 7.0 * 86400.0), ('D', 86400.0)])?;
+    if let Some(time_part) = time_part {
+        seconds += sum_components(time_part, &[('H', 3600.0), ('M', 60.0), ('S', 1.0)])?;
+    }
+    // a huge number of days does not fit into a Duration
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// "1W2D" -> seconds, units is biggest first and each unit may only come once and in that order.
+// None if a number has no unit or the other way around
+fn sum_components(part: &str, units: &[(char, f64)]) -> Option<f64> {
+    let mut seconds = 0.0;
+    let mut next_unit = 0;
+    let mut number = String::new();
+    for c in part.chars() {
+        if c.is_ascii_digit() || c == '.' || c == ',' {
+            number.push(if c == ',' { '.' } else { c });
+            continue;
+        }
+        if number.is_empty() {
+            return None;
+        }
+        let offset = units[next_unit..].iter().position(|(unit, _)| *unit == c)?;
+        let (_, factor) = units[next_unit + offset];
+        seconds += number.parse::<f64>().ok()? * factor;
+        next_unit += offset + 1;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(seconds)
+}
+
+// h:mm:ss
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        total / 3600,
+        (total / 60) % 60,
+        total % 60
+    )
+}
+
+// unix seconds -> (year, month, day) in UTC, civil_from_days by Howard Hinnant
+pub fn civil_date(timestamp: u64) -> (i64, i64, i64) {
+    let days = (timestamp / 86400) as i64;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// unix seconds -> "2024-05-01T13:37:00Z", what the Data API takes for publishedAfter
+pub fn format_rfc3339(timestamp: u64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    let seconds_of_day = timestamp % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        (seconds_of_day / 60) % 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_api_durations() {
+        let samples = [
+            ("PT1H2M3S", 3723),
+            ("PT4M", 240),
+            ("PT45S", 45),
+            ("P1DT2H", 93600),
+            ("P0D", 0),
+        ];
+        for (value, seconds) in samples {
+            assert_eq!(
+                parse_iso8601_duration(value),
+                Some(Duration::from_secs(seconds)),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_broken_durations() {
+        for value in [
+            "",
+            "P",
+            "PT",
+            "PT5",
+            "PTM",
+            "PT3S4M",
+            "PT1M1M",
+            "P1Y",
+            "P99999999999999999999D",
+        ] {
+            assert_eq!(parse_iso8601_duration(value), None, "{value}");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use eframe::egui::{self, Color32};
+use std::fmt;
+
+use crate::yt_dlp_error::{YtDlpFailure, error_message};
+
+// everything that can go wrong in a search or download, Clone so the gui can keep it for the retry
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    MissingApiKey,
+    // the Data API answered with an error status
+    Http {
+        status: u16,
+        message: String,
+    },
+    QuotaExceeded,
+    // no answer at all (offline, dns, timeout)
+    Network(String),
+    Json(String),
+    Spawn {
+        program: String,
+        message: String,
+    },
+    // yt-dlp ran but exited with an error, code is None if it got killed
+    YtDlp {
+        code: Option<i32>,
+        failure: YtDlpFailure,
+        message: String,
+    },
+    ToolMissing(&'static str),
+    // ffmpeg or ffprobe exited with an error, the last line it printed
+    Ffmpeg(String),
+    // the thumbnail could not be turned into a cover
+    Image(String),
+    // a pasted url that leads nowhere
+    NotFound(String),
+    Io(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::MissingApiKey => {
+                write!(
+                    f,
+                    "no YouTube API key, set YT_API in .env or search with yt-dlp"
+                )
+            }
+            AppError::Http { status, message } => {
+                write!(f, "YouTube API answered {status}: {message}")
+            }
+            AppError::QuotaExceeded => write!(
+                f,
+                "the YouTube API quota is used up for today, search with yt-dlp instead"
+            ),
+            AppError::Network(message) => write!(f, "network error: {message}"),
+            AppError::Json(message) => write!(f, "unexpected answer: {message}"),
+            AppError::Spawn { program, message } => {
+                write!(f, "could not start {program}: {message}")
+            }
+            AppError::YtDlp {
+                failure: YtDlpFailure::Other,
+                code: Some(code),
+                message,
+            } => write!(f, "yt-dlp failed with exit code {code}: {message}"),
+            AppError::YtDlp {
+                failure: YtDlpFailure::Other,
+                code: None,
+                message,
+            } => write!(f, "yt-dlp was stopped: {message}"),
+            AppError::YtDlp {
+                failure, message, ..
+            } => write!(f, "{}: {message}", failure.label()),
+            AppError::ToolMissing(tool) => {
+                write!(f, "{tool} not found, check the tools in the settings")
+            }
+            AppError::Ffmpeg(message) => write!(f, "ffmpeg failed: {message}"),
+            AppError::Image(message) => write!(f, "cover art: {message}"),
+            AppError::NotFound(what) => write!(f, "{what} not found"),
+            AppError::Io(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl AppError {
+    pub fn yt_dlp(code: Option<i32>, stderr: &str) -> Self {
+        AppError::YtDlp {
+            code,
+            failure: YtDlpFailure::classify(stderr),
+            message: error_message(stderr),
+        }
+    }
+
+    // what the user can do about it, shown below the error
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AppError::YtDlp { failure, .. } => failure.hint(),
+            _ => None,
+        }
+    }
+
+    // the error in red with the hint below it
+    pub fn show(&self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            ui.colored_label(Color32::RED, self.to_string());
+            if let Some(hint) = self.hint() {
+                ui.colored_label(Color32::GRAY, hint);
+            }
+        });
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            AppError::Json(error.to_string())
+        } else if let Some(status) = error.status() {
+            AppError::Http {
+                status: status.as_u16(),
+                message: error.to_string(),
+            }
+        } else {
+            AppError::Network(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        AppError::Json(error.to_string())
+    }
+}
+
+impl From<image::ImageError> for AppError {
+    fn from(error: image::ImageError) -> Self {
+        AppError::Image(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io(error.to_string())
+    }
+}
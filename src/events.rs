@@ -0,0 +1,139 @@
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use crate::SearchResponse;
+use crate::album::Album;
+use crate::error::AppError;
+use crate::library::LibraryTrack;
+use crate::progress::DownloadProgress;
+use crate::tag_editor::LoadedTags;
+use crate::toast::RetryAction;
+use crate::toolchain::Toolchain;
+
+pub enum AppEvent {
+    // the fetch id of the list and its first page
+    Data(usize, SearchResponse),
+    NextPage(usize, SearchResponse),
+    // job id and its latest progress
+    Progress(usize, DownloadProgress),
+    Error {
+        error: AppError,
+        retry: Option<RetryAction>,
+    },
+    // job id and the path of the finished file
+    Done(usize, Option<String>),
+    Failed(usize, AppError),
+    Tools(Toolchain),
+    // every entry of a playlist, ready to be queued
+    Album(Album),
+    // a file of the tag editor was read or written
+    TagsLoaded(PathBuf, LoadedTags),
+    // every file of the download folder after a scan
+    LibraryScanned(Vec<LibraryTrack>),
+    // the original and the new file of a conversion
+    Converted(PathBuf, Result<LibraryTrack, AppError>),
+}
+
+// progress only keeps the newest line per job, everything else is queued in order
+type LatestProgress = Arc<Mutex<HashMap<usize, DownloadProgress>>>;
+
+// the tokio tasks talk to the gui only through this
+pub struct EventBus {
+    tx: mpsc::UnboundedSender<AppEvent>,
+    rx: mpsc::UnboundedReceiver<AppEvent>,
+    progress: LatestProgress,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            rx,
+            progress: LatestProgress::default(),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn sender(&self, ctx: &egui::Context) -> EventSender {
+        EventSender {
+            tx: self.tx.clone(),
+            progress: self.progress.clone(),
+            ctx: ctx.clone(),
+        }
+    }
+
+    // everything that arrived since the last frame, progress first so a Done of the
+    // same job comes after its last progress
+    pub fn drain(&mut self) -> Vec<AppEvent> {
+        let mut events: Vec<AppEvent> = match self.progress.lock() {
+            Ok(mut progress) => progress
+                .drain()
+                .map(|(job_id, progress)| AppEvent::Progress(job_id, progress))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        while let Ok(event) = self.rx.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+}
+
+// cheap to clone, every send wakes the gui up for the next frame
+#[derive(Clone)]
+pub struct EventSender {
+    tx: mpsc::UnboundedSender<AppEvent>,
+    progress: LatestProgress,
+    ctx: egui::Context,
+}
+
+impl EventSender {
+    // false once the gui is gone, the caller can just stop
+    pub fn send(&self, event: AppEvent) -> bool {
+        let sent = self.tx.send(event).is_ok();
+        if sent {
+            self.ctx.request_repaint();
+        }
+        sent
+    }
+
+    // overwrites the progress of this job that the gui has not seen yet
+    pub fn progress(&self, job_id: usize, progress: DownloadProgress) {
+        if let Ok(mut latest) = self.progress.lock() {
+            latest.insert(job_id, progress);
+        }
+        self.ctx.request_repaint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_coalesced_and_comes_first() {
+        let mut bus = EventBus::default();
+        let events = bus.sender(&egui::Context::default());
+        for percent in [0.1, 0.5, 0.9] {
+            events.progress(
+                7,
+                DownloadProgress {
+                    percent,
+                    ..Default::default()
+                },
+            );
+        }
+        assert!(events.send(AppEvent::Done(7, None)));
+
+        let drained = bus.drain();
+        assert_eq!(drained.len(), 2);
+        assert!(matches!(&drained[0], AppEvent::Progress(7, progress) if progress.percent == 0.9));
+        assert!(matches!(drained[1], AppEvent::Done(7, None)));
+        assert!(bus.drain().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use crate::cover::can_embed;
+use crate::error::AppError;
+use crate::formats::AudioOptions;
+use crate::tags::has_extension;
+
+// runs ffmpeg or ffprobe to the end and hands back stdout
+pub async fn run(program: &Path, args: &[String]) -> Result<Vec<u8>, AppError> {
+    execute(program, args).await.map(|output| output.stdout)
+}
+
+// for filters that report on stderr, like loudnorm
+pub async fn run_stderr(program: &Path, args: &[String]) -> Result<String, AppError> {
+    execute(program, args)
+        .await
+        .map(|output| String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+async fn execute(program: &Path, args: &[String]) -> Result<std::process::Output, AppError> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|error| AppError::Spawn {
+            program: program.to_string_lossy().into_owned(),
+            message: error.to_string(),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .rev()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("no output")
+            .to_string();
+        return Err(AppError::Ffmpeg(message));
+    }
+    Ok(output)
+}
+
+// ffmpeg can not write into its input, so it writes next to it and the result replaces the file.
+// args go between the input and the output, the file keeps its extension and so its container
+pub async fn rewrite(ffmpeg: &Path, path: &Path, args: Vec<String>) -> Result<(), AppError> {
+    let temp = TempFile(temp_path(path));
+    let mut command = vec![
+        "-y".to_string(),
+        "-v".to_string(),
+        "error".to_string(),
+        "-i".to_string(),
+        path.to_string_lossy().into_owned(),
+    ];
+    command.extend(args);
+    command.push(temp.0.to_string_lossy().into_owned());
+
+    run(ffmpeg, &command).await?;
+    std::fs::rename(&temp.0, path)?;
+    Ok(())
+}
+
+// removed when the rewrite fails or a cancelled download drops it halfway
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// writes the file again in another format next to the original, with its tags and,
+// where the new container takes one, its cover. An existing file is not overwritten
+pub async fn convert(
+    ffmpeg: &Path,
+    path: &Path,
+    options: AudioOptions,
+) -> Result<PathBuf, AppError> {
+    let target = path.with_extension(options.format.extension());
+    if target == path {
+        return Ok(target);
+    }
+    let mut args = vec![
+        "-n".to_string(),
+        "-v".to_string(),
+        "error".to_string(),
+        "-i".to_string(),
+        path.to_string_lossy().into_owned(),
+    ];
+    // ogg and opus keep their tags on the stream
+    let metadata = if has_extension(path, &["ogg", "oga", "opus"]) {
+        "0:s:a:0"
+    } else {
+        "0"
+    };
+    args.extend(["-map_metadata", metadata, "-map", "0:a:0"].map(String::from));
+    // the picture stream of an audio file is its cover, the one of a video is the video
+    if can_embed(&target) && can_embed(path) {
+        args.extend(
+            [
+                "-map",
+                "0:v:0?",
+                "-c:v",
+                "copy",
+                "-disposition:v:0",
+                "attached_pic",
+            ]
+            .map(String::from),
+        );
+    } else {
+        args.push("-vn".to_string());
+    }
+    if has_extension(&target, &["mp3"]) {
+        args.extend(["-id3v2_version", "3"].map(String::from));
+    }
+    args.extend(options.ffmpeg_args());
+    args.push(target.to_string_lossy().into_owned());
+    run(ffmpeg, &args).await?;
+    Ok(target)
+}
+
+// song.m4a becomes song.tmp.m4a, ffmpeg picks the container by the last extension
+fn temp_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}.tmp.{}", extension.to_string_lossy()),
+        None => format!("{stem}.tmp"),
+    };
+    path.with_file_name(name)
+}
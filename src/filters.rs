@@ -0,0 +1,501 @@
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::duration::format_rfc3339;
+use crate::search::SearchBackendKind;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResultType {
+    #[default]
+    Video,
+    Channel,
+    Playlist,
+    All,
+}
+
+impl ResultType {
+    pub const ALL: [ResultType; 4] = [
+        ResultType::Video,
+        ResultType::Channel,
+        ResultType::Playlist,
+        ResultType::All,
+    ];
+
+    fn as_arg(&self) -> &'static str {
+        match self {
+            ResultType::Video => "video",
+            ResultType::Channel => "channel",
+            ResultType::Playlist => "playlist",
+            ResultType::All => "video,channel,playlist",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ResultType::Video => "videos",
+            ResultType::Channel => "channels",
+            ResultType::Playlist => "playlists",
+            ResultType::All => "everything",
+        }
+    }
+}
+
+// the ids of the Data API video categories worth offering
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum VideoCategory {
+    Any,
+    #[default]
+    Music,
+    FilmAndAnimation,
+    Gaming,
+    Entertainment,
+    Education,
+}
+
+impl VideoCategory {
+    pub const ALL: [VideoCategory; 6] = [
+        VideoCategory::Any,
+        VideoCategory::Music,
+        VideoCategory::FilmAndAnimation,
+        VideoCategory::Gaming,
+        VideoCategory::Entertainment,
+        VideoCategory::Education,
+    ];
+
+    fn id(&self) -> Option<&'static str> {
+        match self {
+            VideoCategory::Any => None,
+            VideoCategory::Music => Some("10"),
+            VideoCategory::FilmAndAnimation => Some("1"),
+            VideoCategory::Gaming => Some("20"),
+            VideoCategory::Entertainment => Some("24"),
+            VideoCategory::Education => Some("27"),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            VideoCategory::Any => "any category",
+            VideoCategory::Music => "music",
+            VideoCategory::FilmAndAnimation => "film & animation",
+            VideoCategory::Gaming => "gaming",
+            VideoCategory::Entertainment => "entertainment",
+            VideoCategory::Education => "education",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum VideoDuration {
+    #[default]
+    Any,
+    // under 4 minutes
+    Short,
+    // 4 to 20 minutes
+    Medium,
+    // over 20 minutes
+    Long,
+}
+
+impl VideoDuration {
+    pub const ALL: [VideoDuration; 4] = [
+        VideoDuration::Any,
+        VideoDuration::Short,
+        VideoDuration::Medium,
+        VideoDuration::Long,
+    ];
+
+    fn as_arg(&self) -> &'static str {
+        match self {
+            VideoDuration::Any => "any",
+            VideoDuration::Short => "short",
+            VideoDuration::Medium => "medium",
+            VideoDuration::Long => "long",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            VideoDuration::Any => "any length",
+            VideoDuration::Short => "< 4 min",
+            VideoDuration::Medium => "4 - 20 min",
+            VideoDuration::Long => "> 20 min",
+        }
+    }
+}
+
+// turned into publishedAfter, or publishedBefore for the older ones
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum UploadDate {
+    #[default]
+    Any,
+    LastHour,
+    Today,
+    ThisWeek,
+    ThisMonth,
+    ThisYear,
+    OlderThanAYear,
+}
+
+impl UploadDate {
+    pub const ALL: [UploadDate; 7] = [
+        UploadDate::Any,
+        UploadDate::LastHour,
+        UploadDate::Today,
+        UploadDate::ThisWeek,
+        UploadDate::ThisMonth,
+        UploadDate::ThisYear,
+        UploadDate::OlderThanAYear,
+    ];
+
+    fn seconds(&self) -> Option<u64> {
+        match self {
+            UploadDate::Any => None,
+            UploadDate::LastHour => Some(3600),
+            UploadDate::Today => Some(86400),
+            UploadDate::ThisWeek => Some(7 * 86400),
+            UploadDate::ThisMonth => Some(30 * 86400),
+            UploadDate::ThisYear | UploadDate::OlderThanAYear => Some(365 * 86400),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            UploadDate::Any => "any time",
+            UploadDate::LastHour => "last hour",
+            UploadDate::Today => "last 24 hours",
+            UploadDate::ThisWeek => "last week",
+            UploadDate::ThisMonth => "last month",
+            UploadDate::ThisYear => "last year",
+            UploadDate::OlderThanAYear => "older than a year",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchOrder {
+    #[default]
+    Relevance,
+    Date,
+    ViewCount,
+    Rating,
+    Title,
+}
+
+impl SearchOrder {
+    pub const ALL: [SearchOrder; 5] = [
+        SearchOrder::Relevance,
+        SearchOrder::Date,
+        SearchOrder::ViewCount,
+        SearchOrder::Rating,
+        SearchOrder::Title,
+    ];
+
+    fn as_arg(&self) -> &'static str {
+        match self {
+            SearchOrder::Relevance => "relevance",
+            SearchOrder::Date => "date",
+            SearchOrder::ViewCount => "viewCount",
+            SearchOrder::Rating => "rating",
+            SearchOrder::Title => "title",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            SearchOrder::Relevance => "relevance",
+            SearchOrder::Date => "newest",
+            SearchOrder::ViewCount => "most views",
+            SearchOrder::Rating => "rating",
+            SearchOrder::Title => "title",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SafeSearch {
+    None,
+    #[default]
+    Moderate,
+    Strict,
+}
+
+impl SafeSearch {
+    pub const ALL: [SafeSearch; 3] = [SafeSearch::None, SafeSearch::Moderate, SafeSearch::Strict];
+
+    fn as_arg(&self) -> &'static str {
+        match self {
+            SafeSearch::None => "none",
+            SafeSearch::Moderate => "moderate",
+            SafeSearch::Strict => "strict",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum VideoDefinition {
+    #[default]
+    Any,
+    High,
+    Standard,
+}
+
+impl VideoDefinition {
+    pub const ALL: [VideoDefinition; 3] = [
+        VideoDefinition::Any,
+        VideoDefinition::High,
+        VideoDefinition::Standard,
+    ];
+
+    fn as_arg(&self) -> &'static str {
+        match self {
+            VideoDefinition::Any => "any",
+            VideoDefinition::High => "high",
+            VideoDefinition::Standard => "standard",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            VideoDefinition::Any => "any quality",
+            VideoDefinition::High => "HD",
+            VideoDefinition::Standard => "SD",
+        }
+    }
+}
+
+// the filter bar under the search field, only the Data API knows all of them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub result_type: ResultType,
+    pub category: VideoCategory,
+    pub duration: VideoDuration,
+    pub upload_date: UploadDate,
+    pub order: SearchOrder,
+    // ISO 3166-1 alpha-2 like DE, empty for none
+    pub region_code: String,
+    // ISO 639-1 like de, empty for none
+    pub relevance_language: String,
+    pub safe_search: SafeSearch,
+    pub definition: VideoDefinition,
+}
+
+impl SearchFilters {
+    // the search.list parameters on top of q and maxResults
+    pub fn query_params(&self, now: u64) -> Vec<(&'static str, String)> {
+        let mut params = vec![("type", self.result_type.as_arg().to_string())];
+        // the api refuses the video filters unless it only searches videos
+        let videos_only = self.result_type == ResultType::Video;
+        if videos_only && let Some(id) = self.category.id() {
+            params.push(("videoCategoryId", id.to_string()));
+        }
+        if videos_only && self.duration != VideoDuration::Any {
+            params.push(("videoDuration", self.duration.as_arg().to_string()));
+        }
+        if let Some(seconds) = self.upload_date.seconds() {
+            let key = if self.upload_date == UploadDate::OlderThanAYear {
+                "publishedBefore"
+            } else {
+                "publishedAfter"
+            };
+            params.push((key, format_rfc3339(now.saturating_sub(seconds))));
+        }
+        if self.order != SearchOrder::Relevance {
+            params.push(("order", self.order.as_arg().to_string()));
+        }
+        let region_code = self.region_code.trim();
+        if !region_code.is_empty() {
+            params.push(("regionCode", region_code.to_uppercase()));
+        }
+        let language = self.relevance_language.trim();
+        if !language.is_empty() {
+            params.push(("relevanceLanguage", language.to_lowercase()));
+        }
+        params.push(("safeSearch", self.safe_search.as_arg().to_string()));
+        if videos_only && self.definition != VideoDefinition::Any {
+            params.push(("videoDefinition", self.definition.as_arg().to_string()));
+        }
+        params
+    }
+
+    pub fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+
+    // returns true if a filter changed, the search then runs again.
+    // The text fields count once they are confirmed with enter. Filters the backend
+    // or the result type ignores are greyed out
+    pub fn show(&mut self, ui: &mut egui::Ui, backend: SearchBackendKind) -> bool {
+        let api = backend == SearchBackendKind::YoutubeApi;
+        let video_filters = api && self.result_type == ResultType::Video;
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing = egui::vec2(10.0, 5.0);
+            changed |= combo(
+                ui,
+                api,
+                "filter_type",
+                &mut self.result_type,
+                &ResultType::ALL,
+                |t| t.label(),
+            );
+            changed |= combo(
+                ui,
+                video_filters,
+                "filter_category",
+                &mut self.category,
+                &VideoCategory::ALL,
+                |c| c.label(),
+            );
+            changed |= combo(
+                ui,
+                video_filters,
+                "filter_duration",
+                &mut self.duration,
+                &VideoDuration::ALL,
+                |d| d.label(),
+            );
+            changed |= combo(
+                ui,
+                api,
+                "filter_upload",
+                &mut self.upload_date,
+                &UploadDate::ALL,
+                |u| u.label(),
+            );
+            changed |= combo(
+                ui,
+                true,
+                "filter_order",
+                &mut self.order,
+                &SearchOrder::ALL,
+                |o| o.label(),
+            );
+            changed |= combo(
+                ui,
+                video_filters,
+                "filter_definition",
+                &mut self.definition,
+                &VideoDefinition::ALL,
+                |d| d.label(),
+            );
+            changed |= combo(
+                ui,
+                api,
+                "filter_safe",
+                &mut self.safe_search,
+                &SafeSearch::ALL,
+                |s| s.as_arg(),
+            );
+            let region = ui.add_enabled(
+                api,
+                egui::TextEdit::singleline(&mut self.region_code)
+                    .hint_text("region")
+                    .desired_width(70.0),
+            );
+            let language = ui.add_enabled(
+                api,
+                egui::TextEdit::singleline(&mut self.relevance_language)
+                    .hint_text("lang")
+                    .desired_width(60.0),
+            );
+            let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+            changed |= enter && (region.lost_focus() || language.lost_focus());
+            if !api {
+                ui.colored_label(Color32::GRAY, "yt-dlp only sorts by relevance or newest");
+            }
+        });
+        changed
+    }
+}
+
+fn combo<T: Copy + PartialEq>(
+    ui: &mut egui::Ui,
+    enabled: bool,
+    id: &str,
+    value: &mut T,
+    options: &[T],
+    label: impl Fn(&T) -> &'static str,
+) -> bool {
+    let before = *value;
+    ui.add_enabled_ui(enabled, |ui| {
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(label(value))
+            .show_ui(ui, |ui| {
+                for option in options {
+                    ui.selectable_value(value, *option, label(option));
+                }
+            });
+    });
+    before != *value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_filters_only_ask_for_music() {
+        let params = SearchFilters::default().query_params(0);
+        assert_eq!(
+            params,
+            vec![
+                ("type", "video".to_string()),
+                ("videoCategoryId", "10".to_string()),
+                ("safeSearch", "moderate".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_filter_becomes_a_parameter() {
+        let filters = SearchFilters {
+            result_type: ResultType::Video,
+            category: VideoCategory::Any,
+            duration: VideoDuration::Long,
+            upload_date: UploadDate::ThisWeek,
+            order: SearchOrder::ViewCount,
+            region_code: " de ".to_string(),
+            relevance_language: "DE".to_string(),
+            safe_search: SafeSearch::Strict,
+            definition: VideoDefinition::High,
+        };
+        // 2024-05-08 00:00:00 UTC
+        let params = filters.query_params(1_715_126_400);
+        assert_eq!(
+            params,
+            vec![
+                ("type", "video".to_string()),
+                ("videoDuration", "long".to_string()),
+                ("publishedAfter", "2024-05-01T00:00:00Z".to_string()),
+                ("order", "viewCount".to_string()),
+                ("regionCode", "DE".to_string()),
+                ("relevanceLanguage", "de".to_string()),
+                ("safeSearch", "strict".to_string()),
+                ("videoDefinition", "high".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn video_filters_are_left_out_for_channels() {
+        let filters = SearchFilters {
+            result_type: ResultType::Channel,
+            duration: VideoDuration::Short,
+            definition: VideoDefinition::High,
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.query_params(0),
+            vec![
+                ("type", "channel".to_string()),
+                ("safeSearch", "moderate".to_string()),
+            ]
+        );
+    }
+}
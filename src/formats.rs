@@ -0,0 +1,460 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum AudioFormat {
+    Mp3,
+    #[default]
+    M4a,
+    Opus,
+    Vorbis,
+    Flac,
+    Wav,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 6] = [
+        AudioFormat::Mp3,
+        AudioFormat::M4a,
+        AudioFormat::Opus,
+        AudioFormat::Vorbis,
+        AudioFormat::Flac,
+        AudioFormat::Wav,
+    ];
+
+    // the name yt-dlp expects after --audio-format
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Vorbis => "vorbis",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+        }
+    }
+
+    // the file extension yt-dlp gives this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Vorbis => "ogg",
+            format => format.as_arg(),
+        }
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav)
+    }
+
+    pub fn default_quality(&self) -> AudioQuality {
+        match self {
+            AudioFormat::Mp3 => AudioQuality::Vbr(0),
+            AudioFormat::M4a => AudioQuality::Bitrate(256),
+            AudioFormat::Opus => AudioQuality::Bitrate(160),
+            AudioFormat::Vorbis => AudioQuality::Bitrate(192),
+            // ignored for lossless formats, --audio-quality is not passed at all
+            AudioFormat::Flac | AudioFormat::Wav => AudioQuality::Vbr(0),
+        }
+    }
+}
+
+// --audio-quality takes either a VBR level (0 best, 10 worst) or a bitrate like 192K
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AudioQuality {
+    Vbr(u8),
+    Bitrate(u16),
+}
+
+impl AudioQuality {
+    pub const BITRATES: [u16; 7] = [96, 128, 160, 192, 224, 256, 320];
+
+    pub fn as_arg(&self) -> String {
+        match self {
+            AudioQuality::Vbr(level) => level.min(&10).to_string(),
+            AudioQuality::Bitrate(kbps) => format!("{kbps}K"),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            AudioQuality::Vbr(level) => format!("VBR V{level}"),
+            AudioQuality::Bitrate(kbps) => format!("{kbps} kbit/s"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AudioOptions {
+    pub format: AudioFormat,
+    pub quality: AudioQuality,
+}
+
+impl Default for AudioOptions {
+    fn default() -> Self {
+        AudioOptions::for_format(AudioFormat::default())
+    }
+}
+
+impl AudioOptions {
+    pub fn for_format(format: AudioFormat) -> Self {
+        Self {
+            format,
+            quality: format.default_quality(),
+        }
+    }
+
+    // the encoder args of ffmpeg for converting a file of the library, roughly what
+    // yt-dlp does for the same settings
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let codec = match self.format {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::M4a => "aac",
+            AudioFormat::Opus => "libopus",
+            AudioFormat::Vorbis => "libvorbis",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "pcm_s16le",
+        };
+        let mut args = vec!["-c:a".to_string(), codec.to_string()];
+        if self.format.is_lossless() {
+            return args;
+        }
+        match (self.format, self.quality) {
+            (AudioFormat::Mp3, AudioQuality::Vbr(level)) => {
+                args.extend(["-q:a".to_string(), level.min(9).to_string()]);
+            }
+            // vorbis counts the other way round, 10 is the best
+            (AudioFormat::Vorbis, AudioQuality::Vbr(level)) => {
+                args.extend(["-q:a".to_string(), (10 - level.min(10)).to_string()]);
+            }
+            // aac and opus have no quality scale in ffmpeg, V0 is 256 kbit/s and every level 20 less
+            (_, AudioQuality::Vbr(level)) => {
+                let kbps = 256u16.saturating_sub(level as u16 * 20).max(64);
+                args.extend(["-b:a".to_string(), format!("{kbps}k")]);
+            }
+            (_, AudioQuality::Bitrate(kbps)) => {
+                args.extend(["-b:a".to_string(), format!("{kbps}k")]);
+            }
+        }
+        args
+    }
+
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-x".to_string(),
+            "--audio-format".to_string(),
+            self.format.as_arg().to_string(),
+        ];
+        if !self.format.is_lossless() {
+            args.push("--audio-quality".to_string());
+            args.push(self.quality.as_arg());
+        }
+        // yt-dlp can not embed a thumbnail into wav and fails the whole download
+        if self.format != AudioFormat::Wav {
+            args.push("--embed-thumbnail".to_string());
+        }
+        args
+    }
+
+    // settings widget, a new format brings its own default quality
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("audio format:");
+            let before = self.format;
+            egui::ComboBox::from_id_salt("audio_format")
+                .selected_text(self.format.as_arg())
+                .show_ui(ui, |ui| {
+                    for format in AudioFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.as_arg());
+                    }
+                });
+            if before != self.format {
+                self.quality = self.format.default_quality();
+            }
+        });
+        if self.format.is_lossless() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("audio quality:");
+            let mut is_vbr = matches!(self.quality, AudioQuality::Vbr(_));
+            let vbr_clicked = ui.radio_value(&mut is_vbr, true, "VBR").changed();
+            let bitrate_clicked = ui.radio_value(&mut is_vbr, false, "bitrate").changed();
+            if vbr_clicked || bitrate_clicked {
+                self.quality = if is_vbr {
+                    AudioQuality::Vbr(2)
+                } else {
+                    AudioQuality::Bitrate(192)
+                };
+            }
+            match &mut self.quality {
+                AudioQuality::Vbr(level) => {
+                    ui.add(egui::Slider::new(level, 0..=10).text("0 = best"));
+                }
+                AudioQuality::Bitrate(kbps) => {
+                    egui::ComboBox::from_id_salt("audio_bitrate")
+                        .selected_text(format!("{kbps} kbit/s"))
+                        .show_ui(ui, |ui| {
+                            for bitrate in AudioQuality::BITRATES {
+                                ui.selectable_value(kbps, bitrate, format!("{bitrate} kbit/s"));
+                            }
+                        });
+                }
+            }
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum VideoContainer {
+    #[default]
+    Mp4,
+    Mkv,
+    Webm,
+}
+
+impl VideoContainer {
+    pub const ALL: [VideoContainer; 3] = [
+        VideoContainer::Mp4,
+        VideoContainer::Mkv,
+        VideoContainer::Webm,
+    ];
+
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "mp4",
+            VideoContainer::Mkv => "mkv",
+            VideoContainer::Webm => "webm",
+        }
+    }
+
+    // streams that fit the container without re-encoding, mkv takes everything
+    fn preferred_streams(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            VideoContainer::Mp4 => Some(("[ext=mp4]", "[ext=m4a]")),
+            VideoContainer::Webm => Some(("[ext=webm]", "[ext=webm]")),
+            VideoContainer::Mkv => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct VideoOptions {
+    pub max_height: u32,
+    // None takes whatever fps the video has
+    pub max_fps: Option<u32>,
+    pub container: VideoContainer,
+    pub embed_subtitles: bool,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            max_height: 1080,
+            max_fps: None,
+            container: VideoContainer::default(),
+            embed_subtitles: false,
+        }
+    }
+}
+
+impl VideoOptions {
+    pub const HEIGHTS: [u32; 7] = [2160, 1440, 1080, 720, 480, 360, 240];
+
+    // e.g. bv*[height<=1080][fps<=30][ext=mp4]+ba[ext=m4a]/bv*[height<=1080][fps<=30]+ba/b[height<=1080]
+    pub fn format_selector(&self) -> String {
+        let mut limits = format!("[height<={}]", self.max_height);
+        if let Some(fps) = self.max_fps {
+            limits.push_str(&format!("[fps<={fps}]"));
+        }
+        let mut selectors = Vec::new();
+        if let Some((video_ext, audio_ext)) = self.container.preferred_streams() {
+            selectors.push(format!("bv*{limits}{video_ext}+ba{audio_ext}"));
+        }
+        selectors.push(format!("bv*{limits}+ba"));
+        selectors.push(format!("b[height<={}]", self.max_height));
+        selectors.join("/")
+    }
+
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-f".to_string(),
+            self.format_selector(),
+            "--merge-output-format".to_string(),
+            self.container.as_arg().to_string(),
+        ];
+        // webm can not carry a cover image
+        if self.container != VideoContainer::Webm {
+            args.push("--embed-thumbnail".to_string());
+        }
+        if self.embed_subtitles {
+            args.extend(["--embed-subs", "--sub-langs", "all,-live_chat"].map(String::from));
+        }
+        args
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}p {}", self.max_height, self.container.as_arg())
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("max resolution:");
+            egui::ComboBox::from_id_salt("video_height")
+                .selected_text(format!("{}p", self.max_height))
+                .show_ui(ui, |ui| {
+                    for height in VideoOptions::HEIGHTS {
+                        ui.selectable_value(&mut self.max_height, height, format!("{height}p"));
+                    }
+                });
+            ui.add_space(20.0);
+            ui.label("fps:");
+            egui::ComboBox::from_id_salt("video_fps")
+                .selected_text(match self.max_fps {
+                    Some(fps) => format!("max {fps}"),
+                    None => "any".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.max_fps, None, "any");
+                    ui.selectable_value(&mut self.max_fps, Some(60), "max 60");
+                    ui.selectable_value(&mut self.max_fps, Some(30), "max 30");
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("container:");
+            for container in VideoContainer::ALL {
+                ui.radio_value(&mut self.container, container, container.as_arg());
+            }
+        });
+        ui.checkbox(&mut self.embed_subtitles, "embed subtitles");
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum DownloadMode {
+    #[default]
+    Audio,
+    Video,
+}
+
+// what a result row downloads, the row can pick another audio format or video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DownloadKind {
+    Audio(AudioFormat),
+    Video,
+}
+
+impl DownloadKind {
+    pub fn label(&self) -> String {
+        match self {
+            DownloadKind::Audio(format) => format.as_arg().to_string(),
+            DownloadKind::Video => "video".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DownloadOptions {
+    Audio(AudioOptions),
+    Video(VideoOptions),
+}
+
+impl DownloadOptions {
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        match self {
+            DownloadOptions::Audio(audio) => audio.yt_dlp_args(),
+            DownloadOptions::Video(video) => video.yt_dlp_args(),
+        }
+    }
+
+    pub fn kind(&self) -> DownloadKind {
+        match self {
+            DownloadOptions::Audio(audio) => DownloadKind::Audio(audio.format),
+            DownloadOptions::Video(_) => DownloadKind::Video,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            DownloadOptions::Audio(audio) => {
+                format!("{} {}", audio.format.as_arg(), audio.quality.label())
+            }
+            DownloadOptions::Video(video) => video.label(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn audio_args_per_format() {
+        let mp3 = AudioOptions {
+            format: AudioFormat::Mp3,
+            quality: AudioQuality::Vbr(2),
+        };
+        assert_eq!(
+            mp3.yt_dlp_args(),
+            strings(&[
+                "-x",
+                "--audio-format",
+                "mp3",
+                "--audio-quality",
+                "2",
+                "--embed-thumbnail"
+            ])
+        );
+        assert_eq!(
+            AudioOptions::for_format(AudioFormat::Opus).yt_dlp_args(),
+            strings(&[
+                "-x",
+                "--audio-format",
+                "opus",
+                "--audio-quality",
+                "160K",
+                "--embed-thumbnail"
+            ])
+        );
+        // lossless has no quality and wav takes no thumbnail
+        assert_eq!(
+            AudioOptions::for_format(AudioFormat::Flac).yt_dlp_args(),
+            strings(&["-x", "--audio-format", "flac", "--embed-thumbnail"])
+        );
+        assert_eq!(
+            AudioOptions::for_format(AudioFormat::Wav).yt_dlp_args(),
+            strings(&["-x", "--audio-format", "wav"])
+        );
+    }
+
+    #[test]
+    fn video_format_selectors() {
+        assert_eq!(
+            VideoOptions::default().format_selector(),
+            "bv*[height<=1080][ext=mp4]+ba[ext=m4a]/bv*[height<=1080]+ba/b[height<=1080]"
+        );
+        let webm = VideoOptions {
+            max_height: 720,
+            max_fps: Some(30),
+            container: VideoContainer::Webm,
+            embed_subtitles: false,
+        };
+        assert_eq!(
+            webm.format_selector(),
+            "bv*[height<=720][fps<=30][ext=webm]+ba[ext=webm]/bv*[height<=720][fps<=30]+ba/b[height<=720]"
+        );
+        // mkv takes any stream, so there is no preferred one
+        let mkv = VideoOptions {
+            max_height: 2160,
+            container: VideoContainer::Mkv,
+            ..Default::default()
+        };
+        assert_eq!(
+            mkv.format_selector(),
+            "bv*[height<=2160]+ba/b[height<=2160]"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::duration::civil_date;
+use crate::progress::format_bytes;
+
+const HISTORY_FILE: &str = "download_history.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub video_id: String,
+    pub title: String,
+    pub channel: String,
+    pub format: String,
+    pub output_path: String,
+    pub size_bytes: Option<u64>,
+    // unix seconds
+    pub downloaded_at: u64,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        video_id: String,
+        title: String,
+        channel: String,
+        format: String,
+        output_path: String,
+    ) -> Self {
+        let size_bytes = std::fs::metadata(&output_path)
+            .ok()
+            .map(|metadata| metadata.len());
+        let downloaded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Self {
+            video_id,
+            title,
+            channel,
+            format,
+            output_path,
+            size_bytes,
+            downloaded_at,
+        }
+    }
+}
+
+// every finished download, kept as json next to the eframe settings
+#[derive(Default)]
+pub struct DownloadHistory {
+    entries: Vec<HistoryEntry>,
+    video_ids: HashSet<String>,
+    path: Option<PathBuf>,
+    search_text: String,
+    // output paths ticked for the tag editor
+    selected: HashSet<String>,
+}
+
+impl DownloadHistory {
+    pub fn load(storage_dir: Option<PathBuf>) -> Self {
+        let path = storage_dir.map(|dir| dir.join(HISTORY_FILE));
+        let entries: Vec<HistoryEntry> = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(entries) => Some(entries),
+                Err(error) => {
+                    eprintln!("could not read download history: {error}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        let video_ids = entries.iter().map(|entry| entry.video_id.clone()).collect();
+        Self {
+            entries,
+            video_ids,
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn contains(&self, video_id: &str) -> bool {
+        self.video_ids.contains(video_id)
+    }
+
+    pub fn add(&mut self, entry: HistoryEntry) {
+        self.video_ids.insert(entry.video_id.clone());
+        self.entries.push(entry);
+        if let Err(error) = self.save() {
+            eprintln!("could not save download history: {error}");
+        }
+    }
+
+    // a deleted file is no download any more, so its video shows up as new again
+    pub fn remove_files(&mut self, paths: &[PathBuf]) {
+        let before = self.entries.len();
+        self.entries.retain(|entry| {
+            !paths
+                .iter()
+                .any(|path| Path::new(&entry.output_path) == path)
+        });
+        self.selected
+            .retain(|selected| !paths.iter().any(|path| Path::new(selected) == path));
+        if self.entries.len() == before {
+            return;
+        }
+        self.video_ids = self
+            .entries
+            .iter()
+            .map(|entry| entry.video_id.clone())
+            .collect();
+        if let Err(error) = self.save() {
+            eprintln!("could not save download history: {error}");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(&self.entries)?;
+        std::fs::write(path, content)
+    }
+
+    // the files to open in the tag editor, if asked for
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Vec<PathBuf>> {
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search_text)
+                    .hint_text("search title or channel")
+                    .desired_width(400.0),
+            );
+            if !self.selected.is_empty() {
+                ui.add_space(20.0);
+                if ui
+                    .button(format!("edit tags of {} files", self.selected.len()))
+                    .clicked()
+                {
+                    edit = Some(self.selected.iter().map(PathBuf::from).collect());
+                }
+                if ui.button("clear selection").clicked() {
+                    self.selected.clear();
+                }
+            }
+        });
+        ui.add_space(20.0);
+
+        let search = self.search_text.to_lowercase();
+        let matches: Vec<&HistoryEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| {
+                search.is_empty()
+                    || entry.title.to_lowercase().contains(&search)
+                    || entry.channel.to_lowercase().contains(&search)
+            })
+            .collect();
+        if matches.is_empty() {
+            ui.colored_label(Color32::GRAY, "nothing downloaded yet");
+        }
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for entry in matches {
+                    ui.horizontal(|ui| {
+                        let mut selected = self.selected.contains(&entry.output_path);
+                        if ui.checkbox(&mut selected, "").changed() {
+                            if selected {
+                                self.selected.insert(entry.output_path.clone());
+                            } else {
+                                self.selected.remove(&entry.output_path);
+                            }
+                        }
+                        ui.label(&entry.title).on_hover_text(&entry.output_path);
+                        if ui.small_button("edit tags").clicked() {
+                            edit = Some(vec![PathBuf::from(&entry.output_path)]);
+                        }
+                    });
+                    let size = entry
+                        .size_bytes
+                        .map(format_bytes)
+                        .unwrap_or_else(|| "-".to_string());
+                    ui.colored_label(
+                        Color32::GRAY,
+                        format!(
+                            "{}  ·  {}  ·  {}  ·  {}",
+                            entry.channel,
+                            entry.format,
+                            size,
+                            format_timestamp(entry.downloaded_at)
+                        ),
+                    );
+                    ui.add(egui::Separator::default());
+                }
+            });
+        edit
+    }
+}
+
+// unix seconds -> "2024-05-01 13:37" (UTC)
+fn format_timestamp(timestamp: u64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    let seconds_of_day = timestamp % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        seconds_of_day / 3600,
+        (seconds_of_day / 60) % 60
+    )
+}
@@ -0,0 +1,755 @@
+use eframe::egui::{self, Color32, RichText};
+use egui_extras::{Column, TableBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::duration::format_duration;
+use crate::error::AppError;
+use crate::events::{AppEvent, EventSender};
+use crate::ffmpeg;
+use crate::formats::{AudioFormat, AudioOptions};
+use crate::progress::format_bytes;
+use crate::tags::{FileTags, has_extension, probe_file};
+
+const LIBRARY_FILE: &str = "library_index.json";
+// everything yt-dlp writes as audio or video
+const MEDIA_EXTENSIONS: [&str; 11] = [
+    "mp3", "m4a", "opus", "ogg", "oga", "flac", "wav", "aac", "mp4", "webm", "mkv",
+];
+// ffprobe runs at once on the first scan of a big folder
+const PARALLEL_PROBES: usize = 4;
+
+// what a scan sees of a file without opening it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStamp {
+    pub path: PathBuf,
+    // unix seconds
+    pub modified: u64,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn read(path: PathBuf) -> Option<Self> {
+        let metadata = std::fs::metadata(&path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Some(Self {
+            path,
+            modified,
+            size: metadata.len(),
+        })
+    }
+}
+
+// one file of the download folder, the index keeps these between runs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LibraryTrack {
+    pub path: PathBuf,
+    pub modified: u64,
+    pub size: u64,
+    pub tags: FileTags,
+    // seconds
+    pub duration: Option<f64>,
+}
+
+impl LibraryTrack {
+    pub fn new(stamp: FileStamp, tags: FileTags, duration: Option<f64>) -> Self {
+        Self {
+            path: stamp.path,
+            modified: stamp.modified,
+            size: stamp.size,
+            tags,
+            duration,
+        }
+    }
+
+    // untagged files show their file name
+    fn title(&self) -> String {
+        if self.tags.title.is_empty() {
+            self.path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            self.tags.title.clone()
+        }
+    }
+
+    fn artist(&self) -> &str {
+        or_unknown(&self.tags.artist, "unknown artist")
+    }
+
+    fn album(&self) -> &str {
+        or_unknown(&self.tags.album, "no album")
+    }
+
+    // "3/12" and "3" are both track 3
+    fn track_number(&self) -> Option<u32> {
+        self.tags.track.split('/').next()?.trim().parse().ok()
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        filter.is_empty()
+            || [
+                self.title().as_str(),
+                &self.tags.artist,
+                &self.tags.album,
+                &self.tags.genre,
+                &self.tags.year,
+            ]
+            .iter()
+            .any(|value| value.to_lowercase().contains(filter))
+    }
+}
+
+fn or_unknown<'a>(value: &'a str, unknown: &'a str) -> &'a str {
+    if value.is_empty() { unknown } else { value }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortColumn {
+    #[default]
+    Track,
+    Title,
+    Artist,
+    Album,
+    Year,
+    Duration,
+    Size,
+}
+
+impl SortColumn {
+    fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Track => "#",
+            SortColumn::Title => "title",
+            SortColumn::Artist => "artist",
+            SortColumn::Album => "album",
+            SortColumn::Year => "year",
+            SortColumn::Duration => "length",
+            SortColumn::Size => "size",
+        }
+    }
+
+    // artist and album order the groups, the tracks in them stay in track order
+    fn compare(&self, a: &LibraryTrack, b: &LibraryTrack) -> Ordering {
+        let by_track = || {
+            a.track_number()
+                .unwrap_or(u32::MAX)
+                .cmp(&b.track_number().unwrap_or(u32::MAX))
+                .then_with(|| a.title().to_lowercase().cmp(&b.title().to_lowercase()))
+        };
+        match self {
+            SortColumn::Track | SortColumn::Artist | SortColumn::Album => by_track(),
+            SortColumn::Title => a.title().to_lowercase().cmp(&b.title().to_lowercase()),
+            SortColumn::Year => a.tags.year.cmp(&b.tags.year).then_with(by_track),
+            SortColumn::Duration => a
+                .duration
+                .unwrap_or(0.0)
+                .total_cmp(&b.duration.unwrap_or(0.0)),
+            SortColumn::Size => a.size.cmp(&b.size),
+        }
+    }
+}
+
+// the tracks of one album of one artist, as indices into the library
+#[derive(Debug, PartialEq)]
+struct Group {
+    artist: String,
+    album: String,
+    tracks: Vec<usize>,
+}
+
+pub enum LibraryAction {
+    Scan,
+    Reveal(PathBuf),
+    EditTags(Vec<PathBuf>),
+    Convert(Vec<PathBuf>, AudioFormat),
+    Delete(Vec<PathBuf>),
+}
+
+// every audio and video file below the download folder, grouped by artist and album
+#[derive(Default)]
+pub struct Library {
+    tracks: Vec<LibraryTrack>,
+    index_path: Option<PathBuf>,
+    scanning: bool,
+    filter: String,
+    sort: SortColumn,
+    descending: bool,
+    selected: HashSet<PathBuf>,
+    converting: HashSet<PathBuf>,
+    convert_to: AudioFormat,
+    // asked before anything is deleted
+    confirm_delete: Option<Vec<PathBuf>>,
+}
+
+impl Library {
+    pub fn load(storage_dir: Option<PathBuf>) -> Self {
+        let index_path = storage_dir.map(|dir| dir.join(LIBRARY_FILE));
+        let tracks = index_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(tracks) => Some(tracks),
+                Err(error) => {
+                    eprintln!("could not read library index: {error}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            tracks,
+            index_path,
+            ..Default::default()
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.index_path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(&self.tracks)?;
+        std::fs::write(path, content)
+    }
+
+    fn save_or_log(&self) {
+        if let Err(error) = self.save() {
+            eprintln!("could not save library index: {error}");
+        }
+    }
+
+    // only new and changed files are probed, the others come from the index
+    pub fn scan(&mut self, root: PathBuf, ffprobe: PathBuf, events: &EventSender) {
+        if self.scanning {
+            return;
+        }
+        self.scanning = true;
+        let index = self.tracks.clone();
+        let events = events.clone();
+        tokio::spawn(async move {
+            let files = tokio::task::spawn_blocking(move || list_media(&root))
+                .await
+                .unwrap_or_default();
+            let (mut tracks, changed) = split_cached(index, files);
+            let mut changed = changed.into_iter();
+            let mut probes = tokio::task::JoinSet::new();
+            loop {
+                while probes.len() < PARALLEL_PROBES
+                    && let Some(stamp) = changed.next()
+                {
+                    let ffprobe = ffprobe.clone();
+                    probes.spawn(async move {
+                        let probed = probe_file(&ffprobe, &stamp.path).await;
+                        (stamp, probed)
+                    });
+                }
+                let Some(joined) = probes.join_next().await else {
+                    break;
+                };
+                let Ok((stamp, probed)) = joined else {
+                    continue;
+                };
+                // a file ffprobe can not read is still listed, by its name
+                let (tags, duration) = probed.unwrap_or_else(|error| {
+                    eprintln!("could not read {}: {error}", stamp.path.display());
+                    (FileTags::default(), None)
+                });
+                tracks.push(LibraryTrack::new(stamp, tags, duration));
+            }
+            events.send(AppEvent::LibraryScanned(tracks));
+        });
+    }
+
+    pub fn scanned(&mut self, tracks: Vec<LibraryTrack>) {
+        self.scanning = false;
+        self.tracks = tracks;
+        let paths: HashSet<&PathBuf> = self.tracks.iter().map(|track| &track.path).collect();
+        self.selected.retain(|path| paths.contains(path));
+        self.save_or_log();
+    }
+
+    // the new files are added to the library one by one, the originals stay
+    pub fn convert(
+        &mut self,
+        paths: Vec<PathBuf>,
+        options: AudioOptions,
+        ffmpeg: PathBuf,
+        ffprobe: PathBuf,
+        events: &EventSender,
+    ) {
+        self.converting.extend(paths.iter().cloned());
+        let events = events.clone();
+        tokio::spawn(async move {
+            for path in paths {
+                let converted = async {
+                    let target = ffmpeg::convert(&ffmpeg, &path, options).await?;
+                    let stamp = FileStamp::read(target.clone())
+                        .ok_or_else(|| AppError::NotFound(target.display().to_string()))?;
+                    let (tags, duration) = probe_file(&ffprobe, &target).await?;
+                    Ok(LibraryTrack::new(stamp, tags, duration))
+                };
+                let converted = converted.await;
+                if !events.send(AppEvent::Converted(path, converted)) {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn converted(
+        &mut self,
+        source: &Path,
+        converted: Result<LibraryTrack, AppError>,
+    ) -> Option<AppError> {
+        self.converting.remove(source);
+        let track = match converted {
+            Ok(track) => track,
+            Err(error) => return Some(error),
+        };
+        match self
+            .tracks
+            .iter_mut()
+            .find(|known| known.path == track.path)
+        {
+            Some(known) => *known = track,
+            None => self.tracks.push(track),
+        }
+        self.save_or_log();
+        None
+    }
+
+    // the tag editor wrote these tags into the file
+    pub fn update_tags(&mut self, path: &Path, tags: &FileTags) {
+        let Some(track) = self.tracks.iter_mut().find(|track| track.path == path) else {
+            return;
+        };
+        track.tags = tags.clone();
+        if let Some(stamp) = FileStamp::read(path.to_path_buf()) {
+            track.modified = stamp.modified;
+            track.size = stamp.size;
+        }
+        self.save_or_log();
+    }
+
+    // the files that are gone and the errors of the others
+    pub fn delete(&mut self, paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<AppError>) {
+        let mut deleted = Vec::new();
+        let mut errors = Vec::new();
+        for path in paths {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    self.tracks.retain(|track| track.path != path);
+                    self.selected.remove(&path);
+                    deleted.push(path);
+                }
+                Err(error) => errors.push(error.into()),
+            }
+        }
+        self.save_or_log();
+        (deleted, errors)
+    }
+
+    fn groups(&self) -> Vec<Group> {
+        let filter = self.filter.trim().to_lowercase();
+        let mut groups: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+        for (index, track) in self.tracks.iter().enumerate() {
+            if track.matches(&filter) {
+                groups
+                    .entry((track.artist(), track.album()))
+                    .or_default()
+                    .push(index);
+            }
+        }
+        let mut groups: Vec<Group> = groups
+            .into_iter()
+            .map(|((artist, album), mut tracks)| {
+                tracks.sort_by(|a, b| self.sort.compare(&self.tracks[*a], &self.tracks[*b]));
+                let reverse_tracks = !matches!(self.sort, SortColumn::Artist | SortColumn::Album);
+                if self.descending && reverse_tracks {
+                    tracks.reverse();
+                }
+                Group {
+                    artist: artist.to_string(),
+                    album: album.to_string(),
+                    tracks,
+                }
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            let artist = a.artist.to_lowercase().cmp(&b.artist.to_lowercase());
+            let album = a.album.to_lowercase().cmp(&b.album.to_lowercase());
+            match self.sort {
+                SortColumn::Album => album.then(artist),
+                _ => artist.then(album),
+            }
+        });
+        if self.descending && matches!(self.sort, SortColumn::Artist | SortColumn::Album) {
+            groups.reverse();
+        }
+        groups
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<LibraryAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            if self.scanning {
+                ui.spinner();
+                ui.label("scanning");
+            } else if ui.button("rescan").clicked() {
+                action = Some(LibraryAction::Scan);
+            }
+            ui.add_space(20.0);
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("filter title, artist, album, genre or year")
+                    .desired_width(400.0),
+            );
+            ui.add_space(20.0);
+            ui.colored_label(Color32::GRAY, format!("{} files", self.tracks.len()));
+        });
+        ui.add_space(10.0);
+
+        let selected: Vec<PathBuf> = self.selected.iter().cloned().collect();
+        if let Some(paths) = self.confirm_delete.clone() {
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    Color32::RED,
+                    format!("delete {} files from disk?", paths.len()),
+                );
+                if ui.button("delete").clicked() {
+                    action = Some(LibraryAction::Delete(paths));
+                    self.confirm_delete = None;
+                } else if ui.button("cancel").clicked() {
+                    self.confirm_delete = None;
+                }
+            });
+        } else if !selected.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} selected", selected.len()));
+                ui.add_space(20.0);
+                if ui.button("edit tags").clicked() {
+                    action = Some(LibraryAction::EditTags(selected.clone()));
+                }
+                egui::ComboBox::from_id_salt("library_convert")
+                    .selected_text(self.convert_to.as_arg())
+                    .show_ui(ui, |ui| {
+                        for format in AudioFormat::ALL {
+                            ui.selectable_value(&mut self.convert_to, format, format.as_arg());
+                        }
+                    });
+                if ui.button("convert").clicked() {
+                    action = Some(LibraryAction::Convert(selected.clone(), self.convert_to));
+                }
+                if ui.button("delete").clicked() {
+                    self.confirm_delete = Some(selected.clone());
+                }
+                if ui.button("clear selection").clicked() {
+                    self.selected.clear();
+                }
+            });
+        }
+        ui.add_space(10.0);
+
+        let groups = self.groups();
+        if groups.is_empty() && !self.scanning {
+            ui.colored_label(Color32::GRAY, "no files in the download folder");
+            return action;
+        }
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Body) + 6.0;
+        TableBuilder::new(ui)
+            .striped(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .column(Column::exact(30.0))
+            .column(Column::exact(40.0))
+            .column(Column::remainder().at_least(200.0).clip(true))
+            .column(Column::initial(160.0).clip(true))
+            .column(Column::initial(160.0).clip(true))
+            .column(Column::exact(60.0))
+            .column(Column::exact(70.0))
+            .column(Column::exact(80.0))
+            .column(Column::exact(170.0))
+            .header(row_height, |mut header| {
+                header.col(|_| {});
+                for column in [
+                    SortColumn::Track,
+                    SortColumn::Title,
+                    SortColumn::Artist,
+                    SortColumn::Album,
+                    SortColumn::Year,
+                    SortColumn::Duration,
+                    SortColumn::Size,
+                ] {
+                    header.col(|ui| {
+                        let arrow = match (self.sort == column, self.descending) {
+                            (true, false) => " ⏶",
+                            (true, true) => " ⏷",
+                            (false, _) => "",
+                        };
+                        let label = format!("{}{arrow}", column.label());
+                        if ui.selectable_label(self.sort == column, label).clicked() {
+                            self.descending = self.sort == column && !self.descending;
+                            self.sort = column;
+                        }
+                    });
+                }
+                header.col(|_| {});
+            })
+            .body(|mut body| {
+                for group in &groups {
+                    body.row(row_height, |mut row| {
+                        row.col(|ui| {
+                            let paths = group.tracks.iter().map(|index| &self.tracks[*index].path);
+                            let mut all = paths.clone().all(|path| self.selected.contains(path));
+                            if ui.checkbox(&mut all, "").changed() {
+                                for path in paths {
+                                    if all {
+                                        self.selected.insert(path.clone());
+                                    } else {
+                                        self.selected.remove(path);
+                                    }
+                                }
+                            }
+                        });
+                        row.col(|_| {});
+                        row.col(|ui| {
+                            ui.label(
+                                RichText::new(format!("{} — {}", group.artist, group.album))
+                                    .strong(),
+                            );
+                        });
+                        for _ in 0..6 {
+                            row.col(|_| {});
+                        }
+                    });
+                    for index in &group.tracks {
+                        let track = &self.tracks[*index];
+                        body.row(row_height, |mut row| {
+                            row.col(|ui| {
+                                let mut selected = self.selected.contains(&track.path);
+                                if ui.checkbox(&mut selected, "").changed() {
+                                    if selected {
+                                        self.selected.insert(track.path.clone());
+                                    } else {
+                                        self.selected.remove(&track.path);
+                                    }
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.label(&track.tags.track);
+                            });
+                            row.col(|ui| {
+                                ui.label(track.title())
+                                    .on_hover_text(track.path.to_string_lossy());
+                            });
+                            row.col(|ui| {
+                                ui.label(&track.tags.artist);
+                            });
+                            row.col(|ui| {
+                                ui.label(&track.tags.album);
+                            });
+                            row.col(|ui| {
+                                ui.label(&track.tags.year);
+                            });
+                            row.col(|ui| {
+                                // the index file may hold anything
+                                if let Some(duration) = track
+                                    .duration
+                                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                                {
+                                    ui.label(format_duration(duration));
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.label(format_bytes(track.size));
+                            });
+                            row.col(|ui| {
+                                if self.converting.contains(&track.path) {
+                                    ui.spinner();
+                                    return;
+                                }
+                                if ui.small_button("show").clicked() {
+                                    action = Some(LibraryAction::Reveal(track.path.clone()));
+                                }
+                                if ui.small_button("tags").clicked() {
+                                    action =
+                                        Some(LibraryAction::EditTags(vec![track.path.clone()]));
+                                }
+                                if ui.small_button("delete").clicked() {
+                                    self.confirm_delete = Some(vec![track.path.clone()]);
+                                }
+                            });
+                        });
+                    }
+                }
+            });
+        action
+    }
+}
+
+// every media file below root, without the temporary files of ffmpeg and yt-dlp
+fn list_media(root: &Path) -> Vec<FileStamp> {
+    let mut files = Vec::new();
+    let mut folders = vec![root.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = std::fs::read_dir(&folder) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            if file_type.is_dir() {
+                folders.push(path);
+            } else if file_type.is_file()
+                && has_extension(&path, &MEDIA_EXTENSIONS)
+                && !name.contains(".tmp.")
+                && let Some(stamp) = FileStamp::read(path)
+            {
+                files.push(stamp);
+            }
+        }
+    }
+    files
+}
+
+// the tracks of the index that did not change on disk, and the files to probe
+fn split_cached(
+    index: Vec<LibraryTrack>,
+    files: Vec<FileStamp>,
+) -> (Vec<LibraryTrack>, Vec<FileStamp>) {
+    let mut index: HashMap<PathBuf, LibraryTrack> = index
+        .into_iter()
+        .map(|track| (track.path.clone(), track))
+        .collect();
+    let mut kept = Vec::new();
+    let mut changed = Vec::new();
+    for stamp in files {
+        match index.remove(&stamp.path) {
+            Some(track) if track.modified == stamp.modified && track.size == stamp.size => {
+                kept.push(track)
+            }
+            _ => changed.push(stamp),
+        }
+    }
+    (kept, changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(path: &str, artist: &str, album: &str, number: &str, title: &str) -> LibraryTrack {
+        LibraryTrack {
+            path: PathBuf::from(path),
+            modified: 100,
+            size: 1000,
+            tags: FileTags {
+                title: title.to_string(),
+                artist: artist.to_string(),
+                album: album.to_string(),
+                track: number.to_string(),
+                ..Default::default()
+            },
+            duration: None,
+        }
+    }
+
+    fn stamp(path: &str, modified: u64, size: u64) -> FileStamp {
+        FileStamp {
+            path: PathBuf::from(path),
+            modified,
+            size,
+        }
+    }
+
+    #[test]
+    fn only_changed_and_new_files_are_probed() {
+        let index = vec![
+            track("/music/a.mp3", "A", "", "", "a"),
+            track("/music/b.mp3", "B", "", "", "b"),
+            track("/music/gone.mp3", "C", "", "", "gone"),
+        ];
+        let files = vec![
+            stamp("/music/a.mp3", 100, 1000),
+            stamp("/music/b.mp3", 200, 1000),
+            stamp("/music/new.m4a", 300, 10),
+        ];
+        let (kept, changed) = split_cached(index, files);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].path, PathBuf::from("/music/a.mp3"));
+        assert_eq!(
+            changed,
+            vec![
+                stamp("/music/b.mp3", 200, 1000),
+                stamp("/music/new.m4a", 300, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_by_artist_and_album_in_track_order() {
+        let mut library = Library {
+            tracks: vec![
+                track("/m/3.mp3", "Band", "Second", "2/2", "Later"),
+                track("/m/1.mp3", "Band", "First", "10/12", "Ten"),
+                track("/m/2.mp3", "Band", "First", "2/12", "Two"),
+                track("/m/4.mp3", "", "", "", "Loose"),
+                track("/m/5.mp3", "Artist", "First", "1", "Opener"),
+            ],
+            ..Default::default()
+        };
+        let shape = |library: &Library| {
+            library
+                .groups()
+                .iter()
+                .map(|group| {
+                    let titles: Vec<String> = group
+                        .tracks
+                        .iter()
+                        .map(|index| library.tracks[*index].title())
+                        .collect();
+                    (format!("{} - {}", group.artist, group.album), titles)
+                })
+                .collect::<Vec<_>>()
+        };
+        let strings = |titles: &[&str]| titles.iter().map(|title| title.to_string()).collect();
+        assert_eq!(
+            shape(&library),
+            vec![
+                ("Artist - First".to_string(), strings(&["Opener"])),
+                ("Band - First".to_string(), strings(&["Two", "Ten"])),
+                ("Band - Second".to_string(), strings(&["Later"])),
+                ("unknown artist - no album".to_string(), strings(&["Loose"])),
+            ]
+        );
+
+        library.filter = "band".to_string();
+        library.sort = SortColumn::Artist;
+        library.descending = true;
+        assert_eq!(
+            shape(&library),
+            vec![
+                ("Band - Second".to_string(), strings(&["Later"])),
+                ("Band - First".to_string(), strings(&["Two", "Ten"])),
+            ]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+// what a pasted YouTube url points to, everything else is a search query
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrlTarget {
+    Video {
+        id: String,
+        // seconds from t= or start=
+        start: Option<u64>,
+        // the list= of a watch url, mixes (RD...) are left out since the api can not list them
+        playlist_id: Option<String>,
+    },
+    Playlist(String),
+    Channel(ChannelRef),
+    // any other http url, yt-dlp finds out what is behind it
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelRef {
+    // UC...
+    Id(String),
+    // @name, also what /c/name urls lead to nowadays
+    Handle(String),
+    // the old /user/name urls
+    User(String),
+}
+
+const HOSTS: [&str; 6] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "youtube-nocookie.com",
+    "www.youtube-nocookie.com",
+];
+
+impl UrlTarget {
+    // None for text that is no url, a search query then
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let is_url = text.starts_with("http://") || text.starts_with("https://");
+        match Self::parse_youtube(text) {
+            Some(target) => Some(target),
+            None if is_url && !text.contains(char::is_whitespace) => {
+                Some(UrlTarget::Other(text.to_string()))
+            }
+            None => None,
+        }
+    }
+
+    fn parse_youtube(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.contains(char::is_whitespace) {
+            return None;
+        }
+        let rest = text
+            .strip_prefix("https://")
+            .or_else(|| text.strip_prefix("http://"))
+            .unwrap_or(text);
+        let (host, rest) = rest.split_once('/').unwrap_or((rest, ""));
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let query = query.split('#').next().unwrap_or_default();
+        let param = |key: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value)
+                .filter(|value| !value.is_empty())
+        };
+        let segments: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        let start = param("t")
+            .or_else(|| param("start"))
+            .and_then(parse_timestamp);
+        let playlist_id = param("list")
+            .filter(|id| !id.starts_with("RD"))
+            .map(str::to_string);
+
+        let host = host.to_ascii_lowercase();
+        if host == "youtu.be" || host == "www.youtu.be" {
+            let id = segments.first().copied().filter(|id| is_video_id(id))?;
+            return Some(UrlTarget::Video {
+                id: id.to_string(),
+                start,
+                playlist_id,
+            });
+        }
+        if !HOSTS.contains(&host.as_str()) {
+            return None;
+        }
+
+        match segments.as_slice() {
+            ["watch"] => {
+                let id = param("v").filter(|id| is_video_id(id));
+                match (id, playlist_id) {
+                    (Some(id), playlist_id) => Some(UrlTarget::Video {
+                        id: id.to_string(),
+                        start,
+                        playlist_id,
+                    }),
+                    (None, Some(playlist_id)) => Some(UrlTarget::Playlist(playlist_id)),
+                    (None, None) => None,
+                }
+            }
+            ["shorts" | "live" | "embed" | "v", id] if is_video_id(id) => Some(UrlTarget::Video {
+                id: id.to_string(),
+                start,
+                playlist_id,
+            }),
+            ["playlist"] => playlist_id.map(UrlTarget::Playlist),
+            ["channel", id, ..] if id.starts_with("UC") => {
+                Some(UrlTarget::Channel(ChannelRef::Id(id.to_string())))
+            }
+            ["c", name, ..] => Some(UrlTarget::Channel(ChannelRef::Handle(format!("@{name}")))),
+            ["user", name, ..] => Some(UrlTarget::Channel(ChannelRef::User(name.to_string()))),
+            [handle, ..] if handle.starts_with('@') && handle.len() > 1 => {
+                Some(UrlTarget::Channel(ChannelRef::Handle(handle.to_string())))
+            }
+            _ => None,
+        }
+    }
+}
+
+// the url yt-dlp gets for a single video
+pub fn watch_url(video_id: &str) -> String {
+    format!("https://www.youtube.com/watch?v={video_id}")
+}
+
+pub fn playlist_url(playlist_id: &str) -> String {
+    format!("https://www.youtube.com/playlist?list={playlist_id}")
+}
+
+fn is_video_id(id: &str) -> bool {
+    id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// t=90, t=90s, t=1m30s and t=1h2m3s
+fn parse_timestamp(value: &str) -> Option<u64> {
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let factor = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        // a pasted t=9999999999999999h is no time
+        total = number
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(factor)?
+            .checked_add(total)?;
+        number.clear();
+    }
+    number.is_empty().then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(id: &str, start: Option<u64>, playlist_id: Option<&str>) -> Option<UrlTarget> {
+        Some(UrlTarget::Video {
+            id: id.to_string(),
+            start,
+            playlist_id: playlist_id.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn parses_all_url_shapes() {
+        let samples = [
+            (
+                "https://www.youtube.com/watch?v=abc123def45",
+                video("abc123def45", None, None),
+            ),
+            (
+                "youtube.com/watch?feature=share&v=abc123def45&t=1m30s",
+                video("abc123def45", Some(90), None),
+            ),
+            (
+                "https://m.youtube.com/watch?v=abc123def45#comments",
+                video("abc123def45", None, None),
+            ),
+            (
+                "https://youtu.be/abc123def45?si=xyz&t=42",
+                video("abc123def45", Some(42), None),
+            ),
+            (
+                "https://www.youtube.com/shorts/abc123def45",
+                video("abc123def45", None, None),
+            ),
+            (
+                "https://music.youtube.com/watch?v=abc123def45&list=OLAK5uy_abc",
+                video("abc123def45", None, Some("OLAK5uy_abc")),
+            ),
+            (
+                "https://www.youtube.com/watch?v=abc123def45&list=RDabc123def45&start_radio=1",
+                video("abc123def45", None, None),
+            ),
+            (
+                "https://www.youtube.com/playlist?list=PLabc_123",
+                Some(UrlTarget::Playlist("PLabc_123".to_string())),
+            ),
+            (
+                "https://www.youtube.com/@LofiGirl/videos",
+                Some(UrlTarget::Channel(ChannelRef::Handle(
+                    "@LofiGirl".to_string(),
+                ))),
+            ),
+            (
+                "https://www.youtube.com/channel/UCSJ4gkVC6NrvII8umztf0Ow",
+                Some(UrlTarget::Channel(ChannelRef::Id(
+                    "UCSJ4gkVC6NrvII8umztf0Ow".to_string(),
+                ))),
+            ),
+            (
+                "https://www.youtube.com/user/LofiGirl",
+                Some(UrlTarget::Channel(ChannelRef::User("LofiGirl".to_string()))),
+            ),
+            (
+                "https://vimeo.com/123456",
+                Some(UrlTarget::Other("https://vimeo.com/123456".to_string())),
+            ),
+            ("youtube.com/watch?v=tooshort", None),
+            ("lofi hip hop", None),
+        ];
+        for (url, expected) in samples {
+            assert_eq!(UrlTarget::parse(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("75"), Some(75));
+        assert_eq!(parse_timestamp("1h2m3s"), Some(3723));
+        assert_eq!(parse_timestamp("45s"), Some(45));
+        assert_eq!(parse_timestamp("1m30"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+        assert_eq!(parse_timestamp("9999999999999999h"), None);
+        assert_eq!(parse_timestamp("99999999999999999999"), None);
+    }
+}
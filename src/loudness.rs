@@ -0,0 +1,344 @@
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::ffmpeg;
+use crate::formats::{AudioFormat, AudioOptions};
+use crate::tags::{has_extension, write_metadata};
+
+// loudness range of the second pass, the EBU R128 default
+const LOUDNESS_RANGE: f64 = 11.0;
+// ReplayGain 2 gains bring a track to this level
+const REPLAYGAIN_REFERENCE: f64 = -18.0;
+// the R128 gain tags of opus are relative to this level (RFC 7845)
+const OPUS_REFERENCE: f64 = -23.0;
+// these keep ReplayGain tags where players look for them, m4a would need freeform atoms
+// ffmpeg can not write and wav has no tags
+const REPLAYGAIN_EXTENSIONS: [&str; 5] = ["mp3", "flac", "ogg", "oga", "opus"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum LoudnessMode {
+    #[default]
+    Off,
+    // two loudnorm passes, the audio is encoded again
+    Normalize,
+    // only measured, the gain goes into tags the player applies
+    ReplayGain,
+}
+
+impl LoudnessMode {
+    pub const ALL: [LoudnessMode; 3] = [
+        LoudnessMode::Off,
+        LoudnessMode::Normalize,
+        LoudnessMode::ReplayGain,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LoudnessMode::Off => "off",
+            LoudnessMode::Normalize => "normalize",
+            LoudnessMode::ReplayGain => "ReplayGain tags",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct LoudnessOptions {
+    pub mode: LoudnessMode,
+    // integrated loudness in LUFS, streaming sites play at about -14
+    pub target: f64,
+    // highest true peak in dBTP
+    pub true_peak: f64,
+}
+
+impl Default for LoudnessOptions {
+    fn default() -> Self {
+        Self {
+            mode: LoudnessMode::Off,
+            target: -14.0,
+            true_peak: -1.0,
+        }
+    }
+}
+
+pub fn takes_replaygain(format: AudioFormat) -> bool {
+    REPLAYGAIN_EXTENSIONS.contains(&format.extension())
+}
+
+impl LoudnessOptions {
+    // format is the audio format of the settings, ReplayGain is offered only where it works
+    pub fn show(&mut self, ui: &mut egui::Ui, format: AudioFormat) {
+        let replaygain = takes_replaygain(format);
+        ui.horizontal(|ui| {
+            ui.label("loudness:");
+            for mode in LoudnessMode::ALL {
+                let enabled = mode != LoudnessMode::ReplayGain || replaygain;
+                ui.add_enabled_ui(enabled, |ui| {
+                    ui.radio_value(&mut self.mode, mode, mode.label());
+                })
+                .response
+                .on_disabled_hover_text(format!(
+                    "{} files take no ReplayGain tags",
+                    format.extension()
+                ));
+            }
+        });
+        match self.mode {
+            LoudnessMode::Off => {}
+            LoudnessMode::Normalize => {
+                ui.horizontal(|ui| {
+                    ui.label("target:");
+                    ui.add(egui::Slider::new(&mut self.target, -30.0..=-5.0).suffix(" LUFS"));
+                    ui.add_space(20.0);
+                    ui.label("true peak:");
+                    ui.add(egui::Slider::new(&mut self.true_peak, -9.0..=0.0).suffix(" dBTP"));
+                });
+            }
+            LoudnessMode::ReplayGain if !replaygain => {
+                ui.colored_label(
+                    Color32::GRAY,
+                    format!(
+                        "{} files take no ReplayGain tags, they are downloaded without",
+                        format.extension()
+                    ),
+                );
+            }
+            LoudnessMode::ReplayGain => {
+                ui.colored_label(
+                    Color32::GRAY,
+                    "the audio stays as it is, mp3, flac, ogg and opus get the tags",
+                );
+            }
+        }
+    }
+}
+
+// what the first loudnorm pass measured
+#[derive(Debug, Clone, PartialEq)]
+struct Measurement {
+    input_i: f64,
+    input_tp: f64,
+    input_lra: f64,
+    input_thresh: f64,
+    target_offset: f64,
+    // loudnorm works at 192 kHz, the second pass goes back to this
+    sample_rate: Option<u32>,
+}
+
+// the json loudnorm prints at the end of stderr, None for silence (-inf)
+fn parse_measurement(stderr: &str) -> Option<Measurement> {
+    let start = stderr.rfind('{')?;
+    let end = stderr[start..].find('}')? + start;
+    let json: serde_json::Value = serde_json::from_str(&stderr[start..=end]).ok()?;
+    // loudnorm prints its numbers as strings
+    let number = |key: &str| {
+        json.get(key)?
+            .as_str()?
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+    };
+    // Stream #0:0: Audio: opus, 48000 Hz, stereo, fltp
+    let sample_rate = stderr
+        .lines()
+        .filter(|line| line.contains("Audio:"))
+        .flat_map(|line| line.split(','))
+        .find_map(|part| part.trim().strip_suffix(" Hz")?.parse().ok());
+    Some(Measurement {
+        input_i: number("input_i")?,
+        input_tp: number("input_tp")?,
+        input_lra: number("input_lra")?,
+        input_thresh: number("input_thresh")?,
+        target_offset: number("target_offset")?,
+        sample_rate,
+    })
+}
+
+fn first_pass_filter(options: &LoudnessOptions) -> String {
+    format!(
+        "loudnorm=I={}:TP={}:LRA={LOUDNESS_RANGE}:print_format=json",
+        options.target, options.true_peak
+    )
+}
+
+// linear keeps the dynamics when the measurement allows it
+fn second_pass_filter(options: &LoudnessOptions, measured: &Measurement) -> String {
+    format!(
+        "loudnorm=I={}:TP={}:LRA={LOUDNESS_RANGE}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+        options.target,
+        options.true_peak,
+        measured.input_i,
+        measured.input_tp,
+        measured.input_lra,
+        measured.input_thresh,
+        measured.target_offset,
+    )
+}
+
+// opus players read R128_TRACK_GAIN in 1/256 dB, the others the ReplayGain 2 pair
+fn replaygain_tags(path: &Path, measured: &Measurement) -> Vec<(&'static str, String)> {
+    if has_extension(path, &["opus"]) {
+        let gain = ((OPUS_REFERENCE - measured.input_i) * 256.0).round() as i32;
+        return vec![("R128_TRACK_GAIN", gain.to_string())];
+    }
+    let gain = REPLAYGAIN_REFERENCE - measured.input_i;
+    let peak = 10f64.powf(measured.input_tp / 20.0);
+    vec![
+        ("REPLAYGAIN_TRACK_GAIN", format!("{gain:.2} dB")),
+        ("REPLAYGAIN_TRACK_PEAK", format!("{peak:.6}")),
+    ]
+}
+
+async fn measure(
+    ffmpeg: &Path,
+    path: &Path,
+    options: &LoudnessOptions,
+) -> Result<Option<Measurement>, AppError> {
+    let args = [
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-i".to_string(),
+        path.to_string_lossy().into_owned(),
+        "-map".to_string(),
+        "0:a:0".to_string(),
+        "-af".to_string(),
+        first_pass_filter(options),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ];
+    let stderr = ffmpeg::run_stderr(ffmpeg, &args).await?;
+    Ok(parse_measurement(&stderr))
+}
+
+// runs after the tags are written, the encoder args come from the download's audio options
+// so the file keeps its codec, and -map_metadata keeps the tags and the cover
+pub async fn apply(
+    ffmpeg: &Path,
+    path: &Path,
+    options: &LoudnessOptions,
+    audio: &AudioOptions,
+) -> Result<(), AppError> {
+    match options.mode {
+        LoudnessMode::Off => Ok(()),
+        LoudnessMode::ReplayGain => {
+            // with_loudness already leaves these out
+            if !has_extension(path, &REPLAYGAIN_EXTENSIONS) {
+                return Ok(());
+            }
+            let Some(measured) = measure(ffmpeg, path, options).await? else {
+                return Ok(());
+            };
+            write_metadata(ffmpeg, path, &replaygain_tags(path, &measured)).await
+        }
+        LoudnessMode::Normalize => {
+            // a silent track has nothing to normalize
+            let Some(measured) = measure(ffmpeg, path, options).await? else {
+                return Ok(());
+            };
+            let sample_rate = if has_extension(path, &["opus"]) {
+                48000
+            } else {
+                measured.sample_rate.unwrap_or(48000)
+            };
+            let mut args = ["-map", "0", "-map_metadata", "0", "-c", "copy"]
+                .map(String::from)
+                .to_vec();
+            args.extend(audio.ffmpeg_args());
+            args.extend([
+                "-af".to_string(),
+                second_pass_filter(options, &measured),
+                "-ar".to_string(),
+                sample_rate.to_string(),
+            ]);
+            if has_extension(path, &["mp3"]) {
+                args.extend(["-id3v2_version", "3"].map(String::from));
+            }
+            ffmpeg::rewrite(ffmpeg, path, args).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = r#"Input #0, ogg, from 'song.opus':
+  Duration: 00:03:21.50, start: 0.007500, bitrate: 128 kb/s
+  Stream #0:0: Audio: opus, 48000 Hz, stereo, fltp
+Output #0, null, to 'pipe:':
+[Parsed_loudnorm_0 @ 0x5581]
+{
+	"input_i" : "-9.32",
+	"input_tp" : "0.41",
+	"input_lra" : "4.70",
+	"input_thresh" : "-19.43",
+	"output_i" : "-14.02",
+	"output_tp" : "-1.00",
+	"output_lra" : "4.10",
+	"output_thresh" : "-24.10",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.02"
+}
+"#;
+
+    #[test]
+    fn reads_the_first_pass() {
+        let measured = parse_measurement(STDERR).unwrap();
+        assert_eq!(
+            measured,
+            Measurement {
+                input_i: -9.32,
+                input_tp: 0.41,
+                input_lra: 4.7,
+                input_thresh: -19.43,
+                target_offset: 0.02,
+                sample_rate: Some(48000),
+            }
+        );
+        assert!(
+            second_pass_filter(&LoudnessOptions::default(), &measured)
+                .starts_with("loudnorm=I=-14:TP=-1:LRA=11:measured_I=-9.32:")
+        );
+
+        let silence = STDERR.replace("\"-9.32\"", "\"-inf\"");
+        assert_eq!(parse_measurement(&silence), None);
+    }
+
+    #[test]
+    fn replaygain_formats() {
+        let formats: Vec<AudioFormat> = AudioFormat::ALL
+            .into_iter()
+            .filter(|format| takes_replaygain(*format))
+            .collect();
+        assert_eq!(
+            formats,
+            [
+                AudioFormat::Mp3,
+                AudioFormat::Opus,
+                AudioFormat::Vorbis,
+                AudioFormat::Flac
+            ]
+        );
+    }
+
+    #[test]
+    fn replaygain_tags_per_container() {
+        let measured = parse_measurement(STDERR).unwrap();
+        assert_eq!(
+            replaygain_tags(Path::new("song.mp3"), &measured),
+            vec![
+                ("REPLAYGAIN_TRACK_GAIN", "-8.68 dB".to_string()),
+                ("REPLAYGAIN_TRACK_PEAK", "1.048335".to_string()),
+            ]
+        );
+        // (-23 + 9.32) * 256
+        assert_eq!(
+            replaygain_tags(Path::new("song.opus"), &measured),
+            vec![("R128_TRACK_GAIN", "-3502".to_string())]
+        );
+    }
+}
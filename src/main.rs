@@ -1,68 +1,139 @@
 use eframe::egui::{self, Button, Color32, Rect, vec2};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+mod album;
+mod cover;
+mod download;
+mod duration;
+mod error;
+mod events;
+mod ffmpeg;
+mod filters;
+mod formats;
+mod history;
+mod library;
+mod link;
+mod loudness;
+mod media;
+mod platform;
+mod progress;
+mod search;
+mod tag_editor;
+mod tags;
+mod toast;
+mod toolchain;
+mod youtube;
+mod yt_dlp_error;
+use album::{Album, DEFAULT_ALBUM_TEMPLATE};
+use cover::{CoverArt, CoverOptions};
+use download::{DownloadJob, DownloadManager, DownloadState, JobStatus};
+use duration::format_duration;
+use error::AppError;
+use events::{AppEvent, EventBus};
+use filters::SearchFilters;
+use formats::{
+    AudioFormat, AudioOptions, DownloadKind, DownloadMode, DownloadOptions, VideoOptions,
+};
+use history::{DownloadHistory, HistoryEntry};
+use library::{Library, LibraryAction};
+use link::{UrlTarget, watch_url};
+use loudness::LoudnessOptions;
+use search::{ResultSource, SearchBackendKind, YoutubeApiBackend};
+use tag_editor::TagEditor;
+use tags::TrackTags;
+use toast::{RetryAction, Toasts};
+use toolchain::{Tool, ToolPaths, ToolStatus, Toolchain};
+use youtube::YoutubeClient;
 
 // laod .env variables
 use dotenv::dotenv;
-use std::{env, f32};
+use std::f32;
 
-// Api crates
-use reqwest::Client;
 use tokio::{self, io::AsyncBufReadExt}; //asynch
 
 // const
 pub const WIDTH: f32 = 120.0;
 pub const HEIGHT: f32 = 120.0;
+const APP_NAME: &str = "Hier Name";
+const NEXT_PAGE_THRESHOLD: f32 = 200.0;
 
-#[cfg(target_os = "macos")]
-const _DOWNLOAD_PATH: &str = "~/Downloads";
-
-#[cfg(target_os = "windows")]
-const DOWNLOAD_PATH: &str = "%USERPROFILE%\\Downloads";
-
-#[cfg(target_os = "windows")]
-const YT_DLP_BINARY: &str = "./yt_dlp/yt-dlp.exe";
-
-#[cfg(target_os = "macos")]
-const YT_DLP_BINARY: &str = "./yt_dlp/yt-dlp_macos";
-
-enum WorkerMessage {
-    Data(SearchResponse),
-    Progress(u32),
-    Error(String),
-    Done(usize),
-}
-
-struct TokioWorker {
-    tx: tokio::sync::mpsc::Sender<WorkerMessage>,
-    rx: tokio::sync::mpsc::Receiver<WorkerMessage>,
-}
-impl Default for TokioWorker {
-    fn default() -> Self {
-        let (tx, rx) = tokio::sync::mpsc::channel(102);
-        Self { tx, rx }
-    }
-}
-
-#[derive(Default)]
+#[derive(Default, Clone, Copy, PartialEq)]
 enum AppState {
     #[default]
     App,
     Settings,
+    Downloads,
+    History,
+    Library,
+    TagEditor,
+    // dev page, not reachable from the ui
+    #[allow(dead_code)]
     Test,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+// fields missing in older saved settings fall back to the defaults
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 struct SettingsState {
     max_results: i8,
     first_run: bool,
     download_path: String,
+    search_backend: SearchBackendKind,
+    max_parallel_downloads: usize,
+    download_mode: DownloadMode,
+    audio: AudioOptions,
+    video: VideoOptions,
+    tools: ToolPaths,
+    filters: SearchFilters,
+    // where the entries of a playlist download go, see album::render_template
+    album_template: String,
+    cover: CoverOptions,
+    loudness: LoudnessOptions,
 }
-impl SettingsState {
+impl Default for SettingsState {
     fn default() -> Self {
         Self {
             max_results: 8,
             first_run: true,
-            download_path: _DOWNLOAD_PATH.to_string(),
+            download_path: platform::download_dir().to_string_lossy().into_owned(),
+            search_backend: SearchBackendKind::default(),
+            max_parallel_downloads: 2,
+            download_mode: DownloadMode::default(),
+            audio: AudioOptions::default(),
+            video: VideoOptions::default(),
+            tools: ToolPaths::default(),
+            filters: SearchFilters::default(),
+            album_template: DEFAULT_ALBUM_TEMPLATE.to_string(),
+            cover: CoverOptions::default(),
+            loudness: LoudnessOptions::default(),
+        }
+    }
+}
+impl SettingsState {
+    // an empty or unexpanded path (~/Music, %USERPROFILE%\Music) becomes a real absolute one
+    fn resolve_download_path(&mut self) {
+        let path = if self.download_path.trim().is_empty() {
+            platform::download_dir()
+        } else {
+            platform::expand_path(&self.download_path)
+        };
+        self.download_path = path.to_string_lossy().into_owned();
+    }
+
+    // kind is the override of a single row, None uses the download mode
+    fn download_options(&self, kind: Option<DownloadKind>) -> DownloadOptions {
+        match kind {
+            Some(DownloadKind::Audio(format)) if format != self.audio.format => {
+                DownloadOptions::Audio(AudioOptions::for_format(format))
+            }
+            Some(DownloadKind::Audio(_)) => DownloadOptions::Audio(self.audio),
+            Some(DownloadKind::Video) => DownloadOptions::Video(self.video),
+            None => match self.download_mode {
+                DownloadMode::Audio => DownloadOptions::Audio(self.audio),
+                DownloadMode::Video => DownloadOptions::Video(self.video),
+            },
         }
     }
 }
@@ -70,180 +141,713 @@ impl SettingsState {
 #[derive(Default)]
 struct YtGUI {
     data: SearchResponse,
-    search_item: Vec<SearchResponseMeta>,
+    // format override of single results, None uses the settings
+    row_kinds: HashMap<String, DownloadKind>,
+    // tag preview of each video, parsed once when its results arrive
+    tag_previews: HashMap<String, String>,
     search_text: String,
+    // where the shown results come from, the back stack holds the lists above it
+    source: ResultSource,
+    back_stack: Vec<(ResultSource, SearchResponse)>,
+    loading_next_page: bool,
+    // set when a next page failed, the list stops loading more until a retry or a new fetch
+    next_page_failed: bool,
+    // counts the lists shown, a response of an older one is dropped
+    fetch_id: usize,
     side_width: f32,
     settings_state: SettingsState,
     image_loader_installed: bool,
+    fonts_installed: bool,
     app_state: AppState,
-    tokio_worker: TokioWorker,
+    events: EventBus,
+    youtube: YoutubeClient,
+    downloads: DownloadManager,
+    history: DownloadHistory,
+    library: Library,
+    tag_editor: TagEditor,
+    // the view the tag editor was opened from
+    tag_editor_back: AppState,
+    toolchain: Toolchain,
+    toasts: Toasts,
 }
 
 impl YtGUI {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let settings_state: SettingsState = cc
+        let mut settings_state: SettingsState = cc
             .storage
             .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
             .unwrap_or_default();
+        settings_state.resolve_download_path();
 
-        Self {
+        let mut app = Self {
             settings_state,
+            history: DownloadHistory::load(eframe::storage_dir(APP_NAME)),
+            library: Library::load(eframe::storage_dir(APP_NAME)),
             ..Default::default()
+        };
+        app.check_tools(&cc.egui_ctx);
+        app
+    }
+    fn preview_tags(&mut self, items: &[SearchItem]) {
+        for item in items {
+            if let Some(video_id) = &item.id.video_id {
+                let tags = TrackTags::parse(&item.snippet.title, &item.snippet.channel_title);
+                self.tag_previews.insert(video_id.clone(), tags.preview());
+            }
         }
     }
+
     fn search_bar(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
-        egui::Frame::default()
-            .show(ui, |ui| {
-                ui.style_mut().spacing.item_spacing = egui::Vec2 { x: 0.0, y: 0.0 };
-                ui.vertical_centered(|ui| {
-                    ui.horizontal_top(|ui| {
-                        // ui.add_space();
-                        // println!("{}", ui.available_width());
-                        let avaibale_width = ui.available_width();
-                        let searchfield_width = avaibale_width * 0.40;
-                        let search_button_width = avaibale_width * 0.10;
-                        let spacing =
-                            (avaibale_width - (searchfield_width + search_button_width)) / 2.0;
-
-                        ui.add_space(spacing);
-                        let searchfield = ui.add(
-                            egui::TextEdit::singleline(&mut self.search_text)
-                                .hint_text("Search here")
-                                .desired_width(searchfield_width)
-                                .min_size(vec2(330.0, 20.0)),
-                        );
-                        let search_button = ui.add(Button::new("🔍"));
-
-                        if searchfield.clicked() {
-                            searchfield.request_focus();
+        egui::Frame::default().show(ui, |ui| {
+            ui.style_mut().spacing.item_spacing = egui::Vec2 { x: 0.0, y: 0.0 };
+            ui.vertical_centered(|ui| {
+                ui.horizontal_top(|ui| {
+                    // ui.add_space();
+                    // println!("{}", ui.available_width());
+                    let avaibale_width = ui.available_width();
+                    let searchfield_width = avaibale_width * 0.40;
+                    let search_button_width = avaibale_width * 0.10;
+                    let spacing =
+                        (avaibale_width - (searchfield_width + search_button_width)) / 2.0;
+
+                    ui.add_space(spacing);
+                    let searchfield = ui.add(
+                        egui::TextEdit::singleline(&mut self.search_text)
+                            .hint_text("Search here")
+                            .desired_width(searchfield_width)
+                            .min_size(vec2(330.0, 20.0)),
+                    );
+                    let search_button = ui.add(Button::new("🔍"));
+
+                    if searchfield.clicked() {
+                        searchfield.request_focus();
+                    }
+                    // only enter in the search field, the filter fields have their own
+                    if searchfield.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        || search_button.clicked()
+                    {
+                        self.back_stack.clear();
+                        // a pasted url shows what it points to instead of searching for it
+                        if let Some(target) = UrlTarget::parse(&self.search_text) {
+                            self.open(
+                                ctx,
+                                ResultSource::Url {
+                                    text: self.search_text.trim().to_string(),
+                                    target,
+                                },
+                            );
+                        } else {
+                            self.source = ResultSource::Search(self.search_text.clone());
+                            self.spawn_fetch(ctx, None);
                         }
-                        if !searchfield.has_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
-                            || search_button.clicked()
-                        {
-                            let search_string = self.search_text.clone();
-                            let max_reults = self.settings_state.max_results.clone();
-                            let rx = self.tokio_worker.tx.clone();
-                            let ctx_giver = ctx.clone();
+                        self.search_text.clear();
+                    }
 
-                            tokio::spawn(async move {
-                                let mut data =
-                                    call_yt_api(search_string, max_reults).await.unwrap();
-                                let mut ex_video_ids: Vec<String> = Vec::new();
-                                for item in &data.items {
-                                    if let Some(video_id) = &item.id.video_id {
-                                        ex_video_ids.push(video_id.clone());
-                                    }
-                                }
-                                set_video_durration(ex_video_ids, &mut data).await.unwrap();
+                    ui.add_space(spacing);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                        if ui.add(Button::new("⚙")).clicked() {
+                            self.app_state = AppState::Settings;
+                        }
+                        let active_downloads = self.downloads.active_count();
+                        let downloads_label = if active_downloads > 0 {
+                            format!("⬇ {active_downloads}")
+                        } else {
+                            "⬇".to_string()
+                        };
+                        if ui.add(Button::new(downloads_label)).clicked() {
+                            self.app_state = AppState::Downloads;
+                        }
+                        if ui.add(Button::new("🕘")).clicked() {
+                            self.app_state = AppState::History;
+                        }
+                        if ui.add(Button::new("📚")).clicked() {
+                            self.app_state = AppState::Library;
+                            self.scan_library(ctx);
+                        }
+                    });
+                });
+                ui.allocate_space(vec2(ui.available_width(), 10.0));
+                let filters_changed = self
+                    .settings_state
+                    .filters
+                    .show(ui, self.settings_state.search_backend);
+                if filters_changed
+                    && matches!(&self.source, ResultSource::Search(query) if !query.is_empty())
+                {
+                    self.spawn_fetch(ctx, None);
+                }
 
-                                rx.send(WorkerMessage::Data(data)).await.unwrap();
-                                // rx.send({ data })
-                                ctx_giver.request_repaint();
-                            });
+                ui.add_space(40.0);
+                if !self.back_stack.is_empty() {
+                    self.results_header(ctx, ui);
+                    ui.add_space(20.0);
+                }
+                ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                    self.results_list(ctx, ui);
+                    ui.allocate_space(ui.available_size());
+                });
+            });
+        });
+    }
 
-                            self.search_text.clear();
+    // back button and title of an opened channel, playlist or url
+    fn results_header(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let mut back = false;
+        let mut open = None;
+        let mut download_playlist = None;
+        ui.horizontal(|ui| {
+            back = ui.button("← back").clicked();
+            ui.add_space(10.0);
+            ui.heading(self.source.title());
+            match &self.source {
+                // a pasted playlist url lists its entries like an opened playlist
+                ResultSource::Playlist { id, .. }
+                | ResultSource::Url {
+                    target: UrlTarget::Playlist(id),
+                    ..
+                } => {
+                    ui.add_space(20.0);
+                    if ui.button("Download playlist").clicked() {
+                        download_playlist = Some(id.clone());
+                    }
+                }
+                ResultSource::Url {
+                    target:
+                        UrlTarget::Video {
+                            start, playlist_id, ..
+                        },
+                    ..
+                } => {
+                    if let Some(start) = start {
+                        ui.add_space(20.0);
+                        let start = format_duration(std::time::Duration::from_secs(*start));
+                        ui.colored_label(Color32::GRAY, format!("from {start}"));
+                    }
+                    if let Some(playlist_id) = playlist_id {
+                        ui.add_space(20.0);
+                        if ui.button("open playlist").clicked() {
+                            open = Some(ResultSource::Playlist {
+                                id: playlist_id.clone(),
+                                title: playlist_id.clone(),
+                            });
                         }
+                    }
+                }
+                _ => {}
+            }
+        });
+        if back {
+            self.go_back();
+        }
+        if let Some(source) = open {
+            self.open(ctx, source);
+        }
+        if let Some(playlist_id) = download_playlist {
+            self.download_playlist(ctx, playlist_id);
+        }
+    }
 
-                        ui.add_space(spacing);
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                            if ui.add(Button::new("⚙")).clicked() {
-                                self.app_state = AppState::Settings;
+    fn results_list(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        // a clicked channel or playlist, opened after the list is drawn
+        let mut open = None;
+        let mut download_playlist = None;
+        let scroll_output = egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for item in &self.data.items {
+                    ui.horizontal(|ui| {
+                        let thumbnail_url: &str =
+                            if let Some(ref thumb) = item.snippet.thumbnails.default {
+                                &thumb.url
+                            } else {
+                                "notfound"
+                            };
+
+                        let image = egui::Image::from_uri(thumbnail_url)
+                            .fit_to_exact_size(vec2(WIDTH, HEIGHT));
+                        ui.vertical(|ui| {
+                            ui.add(image);
+                            if let Some(duration) = item.video_durration {
+                                ui.label(format_duration(duration));
                             }
                         });
-                    })
-                    .response;
-                    ui.allocate_space(vec2(ui.available_width(), 10.0));
-
-                    ui.add_space(40.0);
-                    ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                        egui::ScrollArea::vertical()
-                            .auto_shrink(false)
-                            .show(ui, |ui| {
-                                for (index, item) in &mut self.data.items.iter().enumerate() {
-                                    self.search_item.push(SearchResponseMeta {
-                                        is_enabled: true,
-                                        download_progress: 0,
+
+                        ui.add_space(40.0);
+                        ui.vertical(|ui| {
+                            ui.label(&item.snippet.title);
+                            ui.colored_label(Color32::GRAY, &item.snippet.channel_title);
+                            let downloaded_before = item
+                                .id
+                                .video_id
+                                .as_ref()
+                                .is_some_and(|video_id| self.history.contains(video_id));
+                            if downloaded_before {
+                                ui.colored_label(Color32::DARK_GREEN, "✔ already downloaded");
+                            }
+                            if let Some(preview) = item
+                                .id
+                                .video_id
+                                .as_ref()
+                                .and_then(|video_id| self.tag_previews.get(video_id))
+                            {
+                                ui.colored_label(Color32::GRAY, format!("🏷 {preview}"))
+                                    .on_hover_text("tags written after the download");
+                            }
+                            ui.add_space(10.0);
+
+                            // channels and playlists only get a button that lists their videos
+                            if let Some(playlist_id) = &item.id.playlist_id {
+                                ui.horizontal(|ui| {
+                                    if ui.button("open playlist").clicked() {
+                                        open = Some(ResultSource::Playlist {
+                                            id: playlist_id.clone(),
+                                            title: item.snippet.title.clone(),
+                                        });
+                                    }
+                                    ui.add_space(10.0);
+                                    if ui.button("Download playlist").clicked() {
+                                        download_playlist = Some(playlist_id.clone());
+                                    }
+                                });
+                                return;
+                            }
+                            if item.id.video_id.is_none()
+                                && let Some(channel_id) = &item.id.channel_id
+                            {
+                                if ui.button("open channel").clicked() {
+                                    open = Some(ResultSource::Channel {
+                                        id: channel_id.clone(),
+                                        title: item.snippet.channel_title.clone(),
                                     });
+                                }
+                                return;
+                            }
+                            let Some(video_id) = &item.id.video_id else {
+                                return;
+                            };
+                            let state = self.downloads.state(video_id);
+                            match state {
+                                DownloadState::Queued(_) => {
                                     ui.horizontal(|ui| {
-                                        let thumbnail_url: &str = if let Some(ref thumb) =
-                                            item.snippet.thumbnails.default
-                                        {
-                                            &thumb.url
-                                        } else {
-                                            "notfound"
-                                        };
-
-                                        let image = egui::Image::from_uri(thumbnail_url)
-                                            .fit_to_exact_size(vec2(WIDTH, HEIGHT));
-                                        ui.vertical(|ui| {
-                                            ui.add(image);
-                                            if let Some(duration) = item.video_durration.as_ref() {
-                                                ui.label(duration);
-                                            }
-                                        });
+                                        ui.add(egui::Spinner::new());
+                                        ui.colored_label(Color32::GRAY, "queued");
+                                    });
+                                    return;
+                                }
+                                DownloadState::Paused(_) => {
+                                    ui.colored_label(Color32::GRAY, "paused");
+                                    return;
+                                }
+                                DownloadState::Downloading(_, progress)
+                                | DownloadState::PostProcessing(_, progress) => {
+                                    ui.add(
+                                        egui::ProgressBar::new(progress.percent)
+                                            .desired_width(300.0)
+                                            .show_percentage(),
+                                    );
+                                    ui.colored_label(Color32::GRAY, progress.status_text());
+                                    return;
+                                }
+                                DownloadState::Failed(job_id, error) => {
+                                    let job_id = *job_id;
+                                    let mut retry_clicked = false;
+                                    ui.horizontal(|ui| {
+                                        error.show(ui);
+                                        ui.add_space(10.0);
+                                        retry_clicked = ui.button("retry").clicked();
+                                    });
+                                    if retry_clicked {
+                                        self.downloads.retry(job_id);
+                                    }
+                                    ui.add_space(10.0);
+                                }
+                                DownloadState::Idle
+                                | DownloadState::Done
+                                | DownloadState::Cancelled => {}
+                            }
 
-                                        ui.add_space(40.0);
-                                        ui.vertical(|ui| {
-                                            ui.label(&item.snippet.title);
-                                            ui.colored_label(
-                                                Color32::GRAY,
-                                                &item.snippet.channel_title,
-                                            );
-                                            ui.add_space(10.0);
-
-                                            if self.search_item[index].is_enabled {
-                                                if ui.add(egui::Button::new("Download")).clicked() {
-                                                    self.search_item[index].is_enabled = false;
-                                                    if let Some(video_id) = &item.id.video_id {
-                                                        let yt_link = format!(
-                                                            "https://www.youtube.com/watch?v={}",
-                                                            video_id
-                                                        );
-                                                        println!(
-                                                            "{}",
-                                                            &self.settings_state.download_path
-                                                        );
-                                                        let path = self
-                                                            .settings_state
-                                                            .download_path
-                                                            .clone();
-                                                        let yt_link = yt_link.clone(); // auch Borrow zu String machen!
-                                                        let item_id = index.clone();
-
-                                                        let tx = self.tokio_worker.tx.clone();
-                                                        tokio::spawn(async move {
-                                                            let error_handle = downlaod_from_dlp(
-                                                                tx, item_id, &yt_link, &path, "aac",
-                                                            )
-                                                            .await;
-                                                            match error_handle {
-                                                                Ok(()) => {}
-                                                                Err(error) => eprintln!(
-                                                                    "download failed with: {error}"
-                                                                ),
-                                                            }
-                                                        });
-                                                    } else {
-                                                        println!(
-                                                            "Fehler Video_id nicht gefunden. Think"
-                                                        );
-                                                    }
-                                                }
-                                            } else {
-                                                ui.add(egui::Spinner::new());
-                                            }
-                                        });
+                            // the format can be changed for this one download
+                            let options = self
+                                .settings_state
+                                .download_options(self.row_kinds.get(video_id).copied());
+                            let mut download_clicked = false;
+                            ui.horizontal(|ui| {
+                                download_clicked = ui
+                                    .add(egui::Button::new("Download"))
+                                    .on_hover_text(options.label())
+                                    .clicked();
+                                ui.add_space(10.0);
+                                let mut kind = options.kind();
+                                egui::ComboBox::from_id_salt(("row_format", video_id))
+                                    .selected_text(kind.label())
+                                    .show_ui(ui, |ui| {
+                                        let kinds = AudioFormat::ALL
+                                            .map(DownloadKind::Audio)
+                                            .into_iter()
+                                            .chain([DownloadKind::Video]);
+                                        for option in kinds {
+                                            ui.selectable_value(&mut kind, option, option.label());
+                                        }
                                     });
-                                    ui.add_space(20.0);
-                                    ui.add(egui::Separator::default());
-                                    ui.add_space(20.0);
+                                if kind != options.kind() {
+                                    self.row_kinds.insert(video_id.clone(), kind);
                                 }
                             });
-                        ui.allocate_space(ui.available_size());
+                            if download_clicked {
+                                let yt_link = item.download_url().unwrap_or_default();
+                                let cover = CoverArt::new(
+                                    self.settings_state.cover,
+                                    item.snippet.thumbnails.best_urls(),
+                                );
+                                self.downloads.enqueue(
+                                    DownloadJob::new(
+                                        video_id.clone(),
+                                        item.snippet.title.clone(),
+                                        item.snippet.channel_title.clone(),
+                                        yt_link,
+                                        self.settings_state.download_path.clone(),
+                                        options,
+                                    )
+                                    .with_cover(cover)
+                                    .with_loudness(self.settings_state.loudness),
+                                );
+                            }
+                        });
                     });
-                });
-            })
-            .response;
+                    ui.add_space(20.0);
+                    ui.add(egui::Separator::default());
+                    ui.add_space(20.0);
+                }
+                if self.loading_next_page {
+                    ui.vertical_centered(|ui| ui.add(egui::Spinner::new()));
+                }
+            });
+
+        // load the next page as soon as the bottom of the list is visible
+        let visible_bottom = scroll_output.state.offset.y + scroll_output.inner_rect.height();
+        if !self.data.items.is_empty()
+            && visible_bottom >= scroll_output.content_size.y - NEXT_PAGE_THRESHOLD
+        {
+            self.load_next_page(ctx);
+        }
+        if let Some(source) = open {
+            self.open(ctx, source);
+        }
+        if let Some(playlist_id) = download_playlist {
+            self.download_playlist(ctx, playlist_id);
+        }
+    }
+
+    // fetches every entry first, queueing happens when the album arrives
+    fn download_playlist(&mut self, ctx: &egui::Context, playlist_id: String) {
+        let youtube = self.youtube.clone();
+        let backend = self.settings_state.search_backend;
+        let yt_dlp = self
+            .toolchain
+            .path(Tool::YtDlp)
+            .map(|path| path.to_path_buf());
+        let events = self.events.sender(ctx);
+        tokio::spawn(async move {
+            let album = backend.album(youtube, yt_dlp, &playlist_id).await;
+            let message = match album {
+                Ok(album) => AppEvent::Album(album),
+                Err(error) => {
+                    eprintln!("playlist download failed with: {error}");
+                    AppEvent::Error {
+                        error,
+                        retry: Some(RetryAction::Album { playlist_id }),
+                    }
+                }
+            };
+            events.send(message);
+        });
+    }
+
+    // entries that are in the history or already in the queue are skipped,
+    // their track numbers stay free so the album keeps its order
+    fn queue_album(&mut self, album: Album) {
+        let options = self.settings_state.download_options(None);
+        for entry in &album.entries {
+            let busy = matches!(
+                self.downloads.state(&entry.video_id),
+                DownloadState::Queued(_)
+                    | DownloadState::Paused(_)
+                    | DownloadState::Downloading(..)
+                    | DownloadState::PostProcessing(..)
+            );
+            if busy || self.history.contains(&entry.video_id) {
+                continue;
+            }
+            let track = album.track(entry, &self.settings_state.album_template);
+            let cover = CoverArt::new(self.settings_state.cover, entry.thumbnails.clone());
+            self.downloads.enqueue(
+                DownloadJob::new(
+                    entry.video_id.clone(),
+                    entry.title.clone(),
+                    entry.channel.clone(),
+                    watch_url(&entry.video_id),
+                    self.settings_state.download_path.clone(),
+                    options,
+                )
+                .in_album(track)
+                .with_cover(cover)
+                .with_loudness(self.settings_state.loudness),
+            );
+        }
+    }
+
+    // shows the videos of a channel or playlist, back returns to the current list
+    fn open(&mut self, ctx: &egui::Context, source: ResultSource) {
+        let previous = std::mem::replace(&mut self.source, source);
+        self.back_stack
+            .push((previous, std::mem::take(&mut self.data)));
+        self.loading_next_page = false;
+        self.spawn_fetch(ctx, None);
+    }
+
+    fn go_back(&mut self) {
+        if let Some((source, data)) = self.back_stack.pop() {
+            self.source = source;
+            self.data = data;
+            self.loading_next_page = false;
+            self.next_page_failed = false;
+            self.fetch_id += 1;
+        }
+    }
+
+    // page_token None loads the first page of the source, otherwise the result gets appended
+    fn spawn_fetch(&mut self, ctx: &egui::Context, page_token: Option<String>) {
+        if page_token.is_none() {
+            self.fetch_id += 1;
+        }
+        self.next_page_failed = false;
+        let fetch_id = self.fetch_id;
+        let source = self.source.clone();
+        let max_reults = self.settings_state.max_results;
+        let backend = self.settings_state.search_backend;
+        let filters = self.settings_state.filters.clone();
+        let youtube = self.youtube.clone();
+        let yt_dlp = self
+            .toolchain
+            .path(Tool::YtDlp)
+            .map(|path| path.to_path_buf());
+        let events = self.events.sender(ctx);
+
+        tokio::spawn(async move {
+            let is_next_page = page_token.is_some();
+            // channels only come from the Data API, whatever the search backend is
+            let result = match &source {
+                ResultSource::Search(query) => {
+                    backend
+                        .search(
+                            youtube,
+                            yt_dlp,
+                            query.clone(),
+                            max_reults,
+                            page_token.clone(),
+                            filters,
+                        )
+                        .await
+                }
+                ResultSource::Playlist { id, .. } => {
+                    backend
+                        .playlist(youtube, yt_dlp, id, max_reults, page_token.clone())
+                        .await
+                }
+                ResultSource::Channel { id, .. } => {
+                    YoutubeApiBackend::new(youtube)
+                        .channel(id, max_reults, page_token.clone())
+                        .await
+                }
+                ResultSource::Url { text, target } => {
+                    backend.lookup(youtube, yt_dlp, text, target).await
+                }
+            };
+            let message = match result {
+                Ok(data) if is_next_page => AppEvent::NextPage(fetch_id, data),
+                Ok(data) => AppEvent::Data(fetch_id, data),
+                Err(error) => {
+                    eprintln!("search failed with: {error}");
+                    AppEvent::Error {
+                        error,
+                        retry: Some(RetryAction::Fetch { source, page_token }),
+                    }
+                }
+            };
+            events.send(message);
+        });
+    }
+
+    fn load_next_page(&mut self, ctx: &egui::Context) {
+        if self.loading_next_page || self.next_page_failed || self.data.next_page_token.is_empty() {
+            return;
+        }
+        self.loading_next_page = true;
+        let page_token = self.data.next_page_token.clone();
+        self.spawn_fetch(ctx, Some(page_token));
+    }
+
+    // the version checks start every binary once, so they run off the gui thread
+    fn check_tools(&mut self, ctx: &egui::Context) {
+        self.toolchain = Toolchain::default();
+        let paths = self.settings_state.tools.clone();
+        let events = self.events.sender(ctx);
+        tokio::spawn(async move {
+            let toolchain =
+                match tokio::task::spawn_blocking(move || Toolchain::locate(&paths)).await {
+                    Ok(toolchain) => toolchain,
+                    Err(error) => {
+                        eprintln!("tool check failed with: {error}");
+                        return;
+                    }
+                };
+            events.send(AppEvent::Tools(toolchain));
+        });
+    }
+
+    fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Done(job_id, output_path) => {
+                let job = self.downloads.finish(job_id, JobStatus::Finished);
+                // a cancelled job has no output path, or was cancelled after yt-dlp was done
+                if let (Some(job), Some(output_path)) = (job, output_path)
+                    && job.status != JobStatus::Cancelled
+                {
+                    self.history.add(HistoryEntry::new(
+                        job.video_id.clone(),
+                        job.title.clone(),
+                        job.channel.clone(),
+                        job.options.label(),
+                        output_path,
+                    ));
+                }
+            }
+            AppEvent::Failed(job_id, error) => {
+                self.downloads.fail(job_id, error);
+            }
+            AppEvent::Progress(job_id, progress) => {
+                self.downloads.update_progress(job_id, progress);
+            }
+            AppEvent::Error { error, retry } => {
+                if let Some(RetryAction::Fetch {
+                    source,
+                    page_token: Some(_),
+                }) = &retry
+                    && *source == self.source
+                {
+                    self.next_page_failed = true;
+                }
+                self.loading_next_page = false;
+                self.toasts.push(error, retry);
+            }
+            // a slow response of a search, filter or list that was left is dropped
+            AppEvent::Data(fetch_id, data) if fetch_id == self.fetch_id => {
+                self.preview_tags(&data.items);
+                self.data = data;
+                self.loading_next_page = false;
+            }
+            AppEvent::NextPage(fetch_id, data)
+                if fetch_id == self.fetch_id && self.loading_next_page =>
+            {
+                self.preview_tags(&data.items);
+                self.data.next_page_token = data.next_page_token;
+                self.data.items.extend(data.items);
+                self.loading_next_page = false;
+            }
+            AppEvent::Data(..) | AppEvent::NextPage(..) => {}
+            AppEvent::Tools(toolchain) => {
+                self.toolchain = toolchain;
+            }
+            AppEvent::Album(album) => self.queue_album(album),
+            AppEvent::TagsLoaded(path, loaded) => {
+                if let Ok((tags, _)) = &loaded {
+                    self.library.update_tags(&path, tags);
+                }
+                if let Some(error) = self.tag_editor.loaded(path, loaded) {
+                    self.toasts.push(error, None);
+                }
+            }
+            AppEvent::LibraryScanned(tracks) => self.library.scanned(tracks),
+            AppEvent::Converted(source, converted) => {
+                if let Some(error) = self.library.converted(&source, converted) {
+                    self.toasts.push(error, None);
+                }
+            }
+        }
+    }
+
+    fn open_tag_editor(&mut self, ctx: &egui::Context, paths: Vec<PathBuf>) {
+        self.tag_editor
+            .open(paths, &self.toolchain, &self.events.sender(ctx));
+        self.tag_editor_back = self.app_state;
+        self.app_state = AppState::TagEditor;
+    }
+
+    fn scan_library(&mut self, ctx: &egui::Context) {
+        match self.toolchain.path(Tool::Ffprobe) {
+            Some(ffprobe) => self.library.scan(
+                PathBuf::from(&self.settings_state.download_path),
+                ffprobe.to_path_buf(),
+                &self.events.sender(ctx),
+            ),
+            None => self.toasts.push(AppError::ToolMissing("ffprobe"), None),
+        }
+    }
+
+    fn library_action(&mut self, ctx: &egui::Context, action: LibraryAction) {
+        match action {
+            LibraryAction::Scan => self.scan_library(ctx),
+            LibraryAction::Reveal(path) => {
+                if let Err(error) = platform::reveal(&path) {
+                    self.toasts.push(error.into(), None);
+                }
+            }
+            LibraryAction::EditTags(paths) => self.open_tag_editor(ctx, paths),
+            LibraryAction::Convert(paths, format) => {
+                let (Some(ffmpeg), Some(ffprobe)) = (
+                    self.toolchain.path(Tool::Ffmpeg),
+                    self.toolchain.path(Tool::Ffprobe),
+                ) else {
+                    self.toasts.push(AppError::ToolMissing("ffmpeg"), None);
+                    return;
+                };
+                // the quality of the settings when they are for the same format
+                let options = if format == self.settings_state.audio.format {
+                    self.settings_state.audio
+                } else {
+                    AudioOptions::for_format(format)
+                };
+                self.library.convert(
+                    paths,
+                    options,
+                    ffmpeg.to_path_buf(),
+                    ffprobe.to_path_buf(),
+                    &self.events.sender(ctx),
+                );
+            }
+            LibraryAction::Delete(paths) => {
+                let (deleted, errors) = self.library.delete(paths);
+                self.history.remove_files(&deleted);
+                for error in errors {
+                    self.toasts.push(error, None);
+                }
+            }
+        }
+    }
+
+    fn retry(&mut self, ctx: &egui::Context, action: RetryAction) {
+        match action {
+            RetryAction::Fetch { source, page_token } => {
+                // a next page only fits the list it was meant for
+                if page_token.is_some() && source != self.source {
+                    return;
+                }
+                self.source = source;
+                self.loading_next_page = page_token.is_some();
+                self.spawn_fetch(ctx, page_token);
+            }
+            RetryAction::Album { playlist_id } => self.download_playlist(ctx, playlist_id),
+        }
     }
 }
 
@@ -252,10 +856,14 @@ impl eframe::App for YtGUI {
         eframe::set_value(storage, eframe::APP_KEY, &self.settings_state);
     }
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if !self.settings_state.first_run {
+        if !self.fonts_installed {
             global_fontsize(ctx);
+            self.fonts_installed = true;
+        }
+        if self.settings_state.first_run {
             self.settings_state.first_run = false;
-            self.settings_state.download_path = _DOWNLOAD_PATH.to_string();
+            self.settings_state.download_path =
+                platform::download_dir().to_string_lossy().into_owned();
         }
         let screen_rect = ctx.screen_rect();
         let panel_size = calc_grid_size(&screen_rect, None);
@@ -265,18 +873,14 @@ impl eframe::App for YtGUI {
             egui_extras::install_image_loaders(ctx);
             self.image_loader_installed = true
         }
-        if let Ok(msg) = self.tokio_worker.rx.try_recv() {
-            match msg {
-                WorkerMessage::Done(index) => {
-                    self.search_item[index].is_enabled = true;
-                }
-                WorkerMessage::Progress(progress_value) => {}
-                WorkerMessage::Error(error_msg) => {}
-                WorkerMessage::Data(data) => {
-                    self.data = data;
-                }
-            }
+        for event in self.events.drain() {
+            self.handle_event(event);
         }
+        self.downloads.start_queued(
+            self.settings_state.max_parallel_downloads,
+            &self.toolchain,
+            &self.events.sender(ctx),
+        );
 
         match self.app_state {
             AppState::App => {
@@ -297,7 +901,7 @@ impl eframe::App for YtGUI {
                                     self.app_state = AppState::App;
                                 }
                                 let button_size = av_space - ui.available_width();
-                                let button_spacer = spacer - button_size;
+                                let _button_spacer = spacer - button_size;
 
                                 // ui.add_space(spacer - button_size);
                                 ui.label("settings");
@@ -309,15 +913,170 @@ impl eframe::App for YtGUI {
                             &mut self.settings_state.max_results,
                             0..=25,
                         ));
-                        if ui.button("press me").clicked() {
-                            let output = std::process::Command::new("pwd").output();
-                            println!("{:?}", output);
+                        ui.add_space(20.0);
+                        ui.horizontal(|ui| {
+                            ui.label("search with:");
+                            for backend in [SearchBackendKind::YoutubeApi, SearchBackendKind::YtDlp]
+                            {
+                                ui.radio_value(
+                                    &mut self.settings_state.search_backend,
+                                    backend,
+                                    backend.label(),
+                                );
+                            }
+                        });
+                        ui.add_space(20.0);
+                        ui.horizontal(|ui| {
+                            ui.label("download:");
+                            ui.radio_value(
+                                &mut self.settings_state.download_mode,
+                                DownloadMode::Audio,
+                                "audio",
+                            );
+                            ui.radio_value(
+                                &mut self.settings_state.download_mode,
+                                DownloadMode::Video,
+                                "video",
+                            );
+                        });
+                        match self.settings_state.download_mode {
+                            DownloadMode::Audio => {
+                                self.settings_state.audio.show(ui);
+                                self.settings_state
+                                    .cover
+                                    .show(ui, self.settings_state.audio.format);
+                                self.settings_state
+                                    .loudness
+                                    .show(ui, self.settings_state.audio.format);
+                            }
+                            DownloadMode::Video => self.settings_state.video.show(ui),
                         }
-                        if ui.button("test me").clicked() {
-                            let output = std::process::Command::new(YT_DLP_BINARY)
-                                .arg("--version")
-                                .output();
-                            println!("{:?}", output);
+                        ui.add_space(20.0);
+                        ui.horizontal(|ui| {
+                            ui.label("download folder:");
+                            let path_field = ui.add(
+                                egui::TextEdit::singleline(&mut self.settings_state.download_path)
+                                    .desired_width(400.0),
+                            );
+                            if path_field.lost_focus() {
+                                self.settings_state.resolve_download_path();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("playlist files:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.settings_state.album_template)
+                                    .hint_text(DEFAULT_ALBUM_TEMPLATE)
+                                    .desired_width(400.0),
+                            )
+                            .on_hover_text(
+                                "<playlist>, <artist>, <nn> and <title>, / starts a folder",
+                            );
+                        });
+                        ui.add_space(20.0);
+                        ui.horizontal(|ui| {
+                            ui.label("parallel downloads:");
+                            ui.add(egui::Slider::new(
+                                &mut self.settings_state.max_parallel_downloads,
+                                1..=8,
+                            ));
+                        });
+                        ui.add_space(20.0);
+                        ui.label("tools:");
+                        if self.toolchain.show(ui, &mut self.settings_state.tools) {
+                            self.check_tools(ctx);
+                        }
+                    },
+                    false,
+                );
+            }
+            AppState::Downloads => {
+                layout(
+                    self.side_width,
+                    ctx,
+                    |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("back to app").clicked() {
+                                self.app_state = AppState::App;
+                            }
+                            ui.add_space(40.0);
+                            ui.label("downloads");
+                        });
+                        ui.add_space(40.0);
+                        if matches!(
+                            self.toolchain.status(Tool::YtDlp),
+                            ToolStatus::Missing | ToolStatus::Broken { .. }
+                        ) {
+                            ui.colored_label(
+                                Color32::RED,
+                                "yt-dlp not found, downloads wait until it is set in the settings",
+                            );
+                            ui.add_space(20.0);
+                        }
+                        self.downloads.show(ui);
+                    },
+                    false,
+                );
+            }
+            AppState::History => {
+                layout(
+                    self.side_width,
+                    ctx,
+                    |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("back to app").clicked() {
+                                self.app_state = AppState::App;
+                            }
+                            ui.add_space(40.0);
+                            ui.label("history");
+                        });
+                        ui.add_space(40.0);
+                        if let Some(paths) = self.history.show(ui) {
+                            self.open_tag_editor(ctx, paths);
+                        }
+                    },
+                    false,
+                );
+            }
+            AppState::Library => {
+                layout(
+                    self.side_width,
+                    ctx,
+                    |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("back to app").clicked() {
+                                self.app_state = AppState::App;
+                            }
+                            ui.add_space(40.0);
+                            ui.label("library");
+                        });
+                        ui.add_space(40.0);
+                        if let Some(action) = self.library.show(ui) {
+                            self.library_action(ctx, action);
+                        }
+                    },
+                    false,
+                );
+            }
+            AppState::TagEditor => {
+                layout(
+                    self.side_width,
+                    ctx,
+                    |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("back").clicked() {
+                                self.app_state = self.tag_editor_back;
+                            }
+                            ui.add_space(40.0);
+                            ui.label("tags");
+                        });
+                        ui.add_space(40.0);
+                        if self.tag_editor.show(ui) {
+                            self.tag_editor.save(
+                                &self.toolchain,
+                                &self.events.sender(ctx),
+                                self.settings_state.cover.size,
+                            );
                         }
                     },
                     false,
@@ -328,20 +1087,30 @@ impl eframe::App for YtGUI {
                     self.side_width,
                     ctx,
                     |ui| {
-                        if ui.button("yt_dlp me").clicked() {
+                        if let Some(yt_dlp) = self.toolchain.path(Tool::YtDlp)
+                            && ui.button("yt_dlp me").clicked()
+                        {
+                            let ffmpeg = self
+                                .toolchain
+                                .path(Tool::Ffmpeg)
+                                .map(|path| path.to_string_lossy().into_owned())
+                                .unwrap_or_default();
                             let args = [
                                 "-x",
                                 "--audio-format",
                                 "aac",
                                 "-o",
-                                "~/Downloads/%(title)s.%(ext)s",
+                                &platform::output_template(
+                                    &self.settings_state.download_path,
+                                    "%(title)s",
+                                ),
                                 "--add-metadata",
                                 "https://www.youtube.com/watch?v=5kfPCxXZPdA",
                                 "--ffmpeg-location",
-                                "./ffmpeg/ffmpeg",
+                                &ffmpeg,
                             ];
-                            let output = std::process::Command::new(YT_DLP_BINARY)
-                                .args(&args)
+                            let output = std::process::Command::new(yt_dlp)
+                                .args(args)
                                 .output()
                                 .expect("Failed halt");
                             println!("{:?}", output);
@@ -362,7 +1131,9 @@ impl eframe::App for YtGUI {
                         if ui.button("test").clicked() {
                             println!("test clicked");
                             tokio::spawn(async move {
-                                test_io().await;
+                                if let Err(error) = test_io().await {
+                                    eprintln!("test_io failed with: {error}");
+                                }
                             });
                         }
                     },
@@ -370,6 +1141,10 @@ impl eframe::App for YtGUI {
                 );
             }
         }
+
+        if let Some(action) = self.toasts.show(ctx) {
+            self.retry(ctx, action);
+        }
     }
 }
 
@@ -414,7 +1189,7 @@ fn calc_grid_size(screen_rect: &Rect, scaling_factor: Option<f32>) -> PanelSize
 
     if central_width >= WIDTH_THRESHOLD {
         side_width = (max_width - WIDTH_THRESHOLD) / scaling_factor.unwrap_or(2.5);
-        central_width = central_width - side_width;
+        central_width -= side_width;
     }
 
     // println!("central:{central_width}, side: {side_width}");
@@ -465,15 +1240,13 @@ async fn main() {
     };
 
     let app = eframe::run_native(
-        "Hier Name",
+        APP_NAME,
         options,
         Box::new(|cc| Ok(Box::new(YtGUI::new(cc)))),
     );
     if let Err(error) = app {
         eprint!("Fehler beim Starten der App: {}", error);
     }
-
-    // let _data = call_yt_api("this is the best song".to_string(), 10);
 }
 
 fn layout<Central>(
@@ -505,96 +1278,6 @@ fn layout<Central>(
     });
 }
 
-async fn call_yt_api(
-    query: String,
-    max_results: i8,
-) -> Result<SearchResponse, Box<dyn std::error::Error>> {
-    let yt_key: String = env::var("YT_API").unwrap();
-    // let yt_key: String = "nein NeinA".to_string();
-
-    let url = format!(
-        "https://www.googleapis.com/youtube/v3/search?part=snippet&q={}&key={}&maxResults={}&type=video&videCategoryId=10",
-        query.replace(" ", "%20"),
-        yt_key,
-        max_results
-    );
-    println!("{url}");
-
-    let client = Client::new();
-    let response = client.get(&url).send().await?;
-    if !response.status().is_success() {
-        println!("Request failed: {}", response.status());
-    }
-    let data: SearchResponse = response.json::<SearchResponse>().await?;
-    println!("Alle Youtube Title: ");
-    let mut index: i8 = 1;
-    for item in &data.items {
-        let video_title = &item.snippet.title;
-        println!("{index}: {video_title}");
-        index += 1;
-    }
-    Ok(data) // main must return something, in this case (). Finish request block and change Result type to SeachResponse
-}
-
-async fn set_video_durration(
-    video_id: Vec<String>,
-    meta_data: &mut SearchResponse,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let final_string = video_id.join(",");
-    let key = env::var("YT_API").unwrap();
-    let url = format!(
-        "https://www.googleapis.com/youtube/v3/videos?part=contentDetails&id={final_string}&key={key}",
-    );
-    println!("{}", url);
-    let client = Client::new();
-    let response = client.get(&url).send().await?;
-    if !response.status().is_success() {
-        println!("Request failed: {}", response.status());
-    }
-    let data: serde_json::Value = response.json::<serde_json::Value>().await?;
-    if let Some(items) = data.get("items").and_then(|v| v.as_array()) {
-        for item in items {
-            if let (Some(video_id), Some(duration)) = (
-                item.get("id").and_then(|v| v.as_str()),
-                item.get("contentDetails")
-                    .and_then(|cd| cd.get("duration"))
-                    .and_then(|d| d.as_str()),
-            ) {
-                println!("{}", duration);
-                let formatted_duration = duration
-                    .replace("PT", "")
-                    .replace("H", ":")
-                    .replace("M", ":")
-                    .replace("S", "");
-                for item in meta_data.items.iter_mut() {
-                    if let Some(obj_video_id) = item.id.video_id.as_ref() {
-                        if obj_video_id == video_id {
-                            item.video_durration = Some(formatted_duration.clone());
-                        }
-                    }
-                }
-            }
-        }
-    }
-    Ok(())
-}
-
-#[derive(Default)]
-struct SearchDuration {
-    items: Vec<SearchDurationItem>,
-}
-
-#[derive(Default)]
-struct SearchDurationItem {
-    video_id: String,
-    video_durration: String,
-}
-
-struct SearchResponseMeta {
-    is_enabled: bool,
-    download_progress: usize,
-}
-
 #[derive(Debug, Serialize, Deserialize, Default)]
 struct SearchResponse {
     #[serde(default)]
@@ -618,9 +1301,18 @@ pub struct SearchItem {
     pub id: Id,
     pub snippet: Snippet,
     #[serde(skip)]
-    pub is_enabled: bool,
+    pub video_durration: Option<std::time::Duration>,
+    // the page yt-dlp downloads, None for youtube items that only have their video id
     #[serde(skip)]
-    pub video_durration: Option<String>,
+    pub url: Option<String>,
+}
+
+impl SearchItem {
+    pub fn download_url(&self) -> Option<String> {
+        self.url
+            .clone()
+            .or_else(|| self.id.video_id.as_deref().map(watch_url))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -655,14 +1347,35 @@ pub struct Snippet {
     #[serde(rename = "liveBroadcastContent")]
     pub live_broadcast_content: String,
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Thumbnails {
     pub default: Option<ThumbnailData>,
     pub medium: Option<ThumbnailData>,
     pub high: Option<ThumbnailData>,
+    #[serde(default)]
+    pub standard: Option<ThumbnailData>,
+    #[serde(default)]
+    pub maxres: Option<ThumbnailData>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Thumbnails {
+    // biggest first, the cover art takes the first one that loads
+    pub fn best_urls(&self) -> Vec<String> {
+        [
+            &self.maxres,
+            &self.standard,
+            &self.high,
+            &self.medium,
+            &self.default,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|thumb| thumb.url.clone())
+        .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ThumbnailData {
     pub url: String,
     pub width: Option<u32>,
@@ -674,68 +1387,6 @@ struct PanelSize {
     _central_width: f32,
 }
 
-async fn downlaod_from_dlp(
-    rx: tokio::sync::mpsc::Sender<WorkerMessage>,
-    item_id: usize,
-    url: &String,
-    download_path: &String,
-    audio_format: &'static str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let download_string = format!("{download_path}/%(title)s.%(ext)s");
-
-    let command = [
-        "-x",
-        "--audio-format",
-        { &audio_format },
-        "-o",
-        { &download_string },
-        "--add-metadata",
-        "--embed-thumbnail",
-        "--ffmpeg-location",
-        "./ffmpeg/ffmpeg",
-        "--progress-template",
-        "download:%(progress)j",
-        "--progress-template",
-        "postprocess:%(progress)j",
-        { &url },
-    ];
-
-    let mut output = tokio::process::Command::new(YT_DLP_BINARY)
-        .args(&command)
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()?;
-
-    if let Some(stdout) = output.stdout.take() {
-        let reader = tokio::io::BufReader::new(stdout);
-        let mut lines = reader.lines();
-
-        while let Some(line) = lines.next_line().await? {
-            match serde_json::from_str::<serde_json::Value>(&line) {
-                Ok(progress) => {
-                    if let Some(procent) = progress.get("_percent_str") {
-                        println!("{procent}")
-                    }
-                }
-                Err(_) => {}
-            }
-            // let progress: serde_json::Value = serde_json::from_str(&line)?;
-            // println!("{}", progress);
-        }
-    }
-    if let Some(stderr) = output.stderr.take() {
-        let reader = tokio::io::BufReader::new(stderr);
-        let mut lines = reader.lines();
-
-        while let Some(line) = lines.next_line().await? {
-            println!("{line}");
-        }
-    }
-
-    rx.send(WorkerMessage::Done(item_id)).await.unwrap();
-    Ok(())
-}
-
 async fn test_io() -> Result<(), Box<dyn std::error::Error>> {
     println!("starting test_io");
     let mut child = tokio::process::Command::new("ping")
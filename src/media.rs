@@ -0,0 +1,175 @@
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::{Id, SearchItem, Snippet, ThumbnailData, Thumbnails};
+
+// the most entries one pasted album or playlist url lists
+const MAX_ENTRIES: &str = "50";
+
+// one downloadable thing of any site yt-dlp supports (SoundCloud, Bandcamp, Vimeo, podcasts, ...)
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct MediaItem {
+    pub id: String,
+    // Youtube, Soundcloud, Bandcamp, ...
+    #[serde(default)]
+    pub extractor_key: String,
+    pub webpage_url: Option<String>,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // sites with music metadata fill these, the others only know the uploader
+    #[serde(default)]
+    pub track: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub uploader: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+}
+
+impl MediaItem {
+    // download state and history are keyed by this, ids of other sites could clash with youtube ones
+    pub fn key(&self) -> String {
+        if self.extractor_key.eq_ignore_ascii_case("youtube") {
+            self.id.clone()
+        } else {
+            format!("{}:{}", self.extractor_key.to_lowercase(), self.id)
+        }
+    }
+
+    pub fn into_search_item(self) -> SearchItem {
+        let key = self.key();
+        let thumbnail = self.thumbnail.map(|url| ThumbnailData {
+            url,
+            width: None,
+            height: None,
+        });
+        let channel_title = self
+            .artist
+            .or(self.channel)
+            .or(self.uploader)
+            .unwrap_or_default();
+        let title = match self.track {
+            Some(track) if !track.is_empty() => track,
+            _ => self.title,
+        };
+        SearchItem {
+            kind: format!("yt-dlp#{}", self.extractor_key.to_lowercase()),
+            etag: String::new(),
+            id: Id {
+                kind: "yt-dlp#media".to_string(),
+                video_id: Some(key),
+                channel_id: None,
+                playlist_id: None,
+            },
+            snippet: Snippet {
+                published_at: String::new(),
+                channel_id: String::new(),
+                title,
+                description: self.description.unwrap_or_default(),
+                thumbnails: Thumbnails {
+                    default: thumbnail.clone(),
+                    medium: thumbnail.clone(),
+                    high: thumbnail,
+                    ..Default::default()
+                },
+                channel_title,
+                live_broadcast_content: "none".to_string(),
+            },
+            // negative, NaN or huge durations are none
+            video_durration: self
+                .duration
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
+            url: self.webpage_url,
+        }
+    }
+}
+
+// everything behind a url, one item for a track and one per entry for albums and playlists
+// no_playlist keeps a watch url with &list= to its one video
+pub async fn dump_json(
+    yt_dlp: &Path,
+    url: &str,
+    no_playlist: bool,
+) -> Result<Vec<MediaItem>, AppError> {
+    let mut args = vec![
+        "--dump-json",
+        "--ignore-errors",
+        "--playlist-end",
+        MAX_ENTRIES,
+    ];
+    if no_playlist {
+        args.push("--no-playlist");
+    }
+    args.push(url);
+    let output = tokio::process::Command::new(yt_dlp)
+        .args(args)
+        .output()
+        .await
+        .map_err(|error| AppError::Spawn {
+            program: "yt-dlp".to_string(),
+            message: error.to_string(),
+        })?;
+    let items = parse_dump(&String::from_utf8_lossy(&output.stdout));
+    // with --ignore-errors a broken entry does not fail the whole album
+    if items.is_empty() {
+        return Err(AppError::yt_dlp(
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(items)
+}
+
+fn parse_dump(stdout: &str) -> Vec<MediaItem> {
+    stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        // warnings and broken entries are skipped
+        .filter_map(|line| serde_json::from_str::<MediaItem>(line).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_of_other_sites_get_their_own_key() {
+        let stdout = r#"{"id": "1234567", "extractor_key": "Soundcloud", "webpage_url": "https://soundcloud.com/artist/song", "title": "Artist - Song", "uploader": "artist", "duration": 201.5, "thumbnail": "https://i1.sndcdn.com/artworks-t500x500.jpg"}
+{"id": "abc123def45", "extractor_key": "Youtube", "webpage_url": "https://www.youtube.com/watch?v=abc123def45", "title": "Video", "channel": "Channel", "uploader": "Uploader"}
+{"id": "2981771", "extractor_key": "Bandcamp", "webpage_url": "https://band.bandcamp.com/track/song", "title": "Band - Song", "track": "Song", "artist": "Band"}
+not json
+"#;
+        let items: Vec<SearchItem> = parse_dump(stdout)
+            .into_iter()
+            .map(MediaItem::into_search_item)
+            .collect();
+        assert_eq!(items.len(), 3);
+
+        assert_eq!(items[0].id.video_id.as_deref(), Some("soundcloud:1234567"));
+        assert_eq!(
+            items[0].url.as_deref(),
+            Some("https://soundcloud.com/artist/song")
+        );
+        assert_eq!(items[0].snippet.channel_title, "artist");
+        assert_eq!(
+            items[0].video_durration,
+            Some(Duration::from_millis(201_500))
+        );
+
+        assert_eq!(items[1].id.video_id.as_deref(), Some("abc123def45"));
+        assert_eq!(items[1].snippet.channel_title, "Channel");
+
+        assert_eq!(items[2].snippet.title, "Song");
+        assert_eq!(items[2].snippet.channel_title, "Band");
+    }
+}
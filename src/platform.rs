@@ -0,0 +1,229 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "windows")]
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("USERPROFILE")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+// XDG_DOWNLOAD_DIR from the environment or user-dirs.dirs, ~/Downloads otherwise
+#[cfg(target_os = "linux")]
+pub fn download_dir() -> PathBuf {
+    if let Some(dir) = env::var("XDG_DOWNLOAD_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+    {
+        return expand_path(&dir);
+    }
+    let config_dir = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")));
+    let user_dirs = config_dir
+        .and_then(|dir| std::fs::read_to_string(dir.join("user-dirs.dirs")).ok())
+        .and_then(|content| parse_user_dirs(&content, "XDG_DOWNLOAD_DIR"));
+    match user_dirs {
+        Some(dir) => expand_path(&dir),
+        None => default_download_dir(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn download_dir() -> PathBuf {
+    default_download_dir()
+}
+
+fn default_download_dir() -> PathBuf {
+    match home_dir() {
+        Some(home) => home.join("Downloads"),
+        None => env::current_dir().unwrap_or_default(),
+    }
+}
+
+// user-dirs.dirs lines look like XDG_DOWNLOAD_DIR="$HOME/Downloads"
+#[cfg(target_os = "linux")]
+fn parse_user_dirs(content: &str, key: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// expands ~, $VAR, ${VAR} and %VAR% and makes the path absolute.
+// Unknown variables are left as they are
+pub fn expand_path(path: &str) -> PathBuf {
+    let path = path.trim();
+    let expanded = expand_env_vars(path, |name| env::var(name).ok());
+
+    let expanded = if expanded == "~" {
+        home_dir().unwrap_or_default()
+    } else if let Some(rest) = expanded
+        .strip_prefix("~/")
+        .or_else(|| expanded.strip_prefix("~\\"))
+    {
+        home_dir().unwrap_or_default().join(rest)
+    } else {
+        PathBuf::from(expanded)
+    };
+
+    if expanded.is_absolute() {
+        expanded
+    } else {
+        env::current_dir()
+            .map(|cwd| cwd.join(&expanded))
+            .unwrap_or(expanded)
+    }
+}
+
+// lookup gives the value of a variable, env::var outside the tests
+fn expand_env_vars(path: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find(['$', '%']) {
+        result.push_str(&rest[..start]);
+        let marker = &rest[start..];
+        let (name, consumed) = if let Some(braced) = marker.strip_prefix("${") {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 3),
+                None => ("", 0),
+            }
+        } else if let Some(percent) = marker.strip_prefix('%') {
+            match percent.find('%') {
+                Some(end) => (&percent[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let name_len = marker[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(marker.len() - 1);
+            (&marker[1..1 + name_len], name_len + 1)
+        };
+
+        match lookup(name) {
+            Some(value) if !name.is_empty() => {
+                result.push_str(&value);
+                rest = &marker[consumed..];
+            }
+            _ => {
+                // not a variable we know, keep the character and go on
+                result.push_str(&marker[..1]);
+                rest = &marker[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// the -o template for yt-dlp inside the download folder, file_name is a yt-dlp template
+// without extension. A % in the folder name would start a template field so it gets doubled
+pub fn output_template(download_path: &str, file_name: &str) -> String {
+    let folder = expand_path(download_path)
+        .to_string_lossy()
+        .replace('%', "%%");
+    PathBuf::from(folder)
+        .join(format!("{file_name}.%(ext)s"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+// shows the file in the file manager, selected where the platform can do that
+#[cfg(target_os = "windows")]
+pub fn reveal(path: &Path) -> std::io::Result<()> {
+    let mut select = std::ffi::OsString::from("/select,");
+    select.push(path);
+    std::process::Command::new("explorer").arg(select).spawn()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reveal(path: &Path) -> std::io::Result<()> {
+    std::process::Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .spawn()?;
+    Ok(())
+}
+
+// xdg-open has no way to select a file, so the folder is opened
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn reveal(path: &Path) -> std::io::Result<()> {
+    let folder = path.parent().unwrap_or(path);
+    std::process::Command::new("xdg-open").arg(folder).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_variables() {
+        let expand = |path| {
+            expand_env_vars(path, |name| {
+                (name == "MUSIC").then(|| "/srv/music".to_string())
+            })
+        };
+        assert_eq!(expand("$MUSIC/yt"), "/srv/music/yt");
+        assert_eq!(expand("${MUSIC}yt"), "/srv/musicyt");
+        assert_eq!(expand("%MUSIC%\\yt"), "/srv/music\\yt");
+        // unknown variables and lone markers stay as they are
+        assert_eq!(expand("$UNSET/yt"), "$UNSET/yt");
+        assert_eq!(expand("${UNSET}"), "${UNSET}");
+        assert_eq!(expand("%UNSET%"), "%UNSET%");
+        assert_eq!(expand("100% $ ${"), "100% $ ${");
+    }
+
+    #[test]
+    fn expands_home_and_relative_paths() {
+        let home = home_dir().unwrap_or_default();
+        assert_eq!(expand_path("~"), home);
+        assert_eq!(expand_path(" ~/Music "), home.join("Music"));
+        assert_eq!(expand_path("/srv/music"), PathBuf::from("/srv/music"));
+        assert_eq!(
+            expand_path("music"),
+            env::current_dir().unwrap().join("music")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_user_dirs() {
+        let content = r#"
+# XDG_DOWNLOAD_DIR="$HOME/Old"
+XDG_DESKTOP_DIR="$HOME/Desktop"
+XDG_DOWNLOAD_DIR="$HOME/Downloads"
+XDG_MUSIC_DIR=/srv/music
+XDG_VIDEOS_DIR=""
+"#;
+        assert_eq!(
+            parse_user_dirs(content, "XDG_DOWNLOAD_DIR").as_deref(),
+            Some("$HOME/Downloads")
+        );
+        assert_eq!(
+            parse_user_dirs(content, "XDG_MUSIC_DIR").as_deref(),
+            Some("/srv/music")
+        );
+        assert_eq!(parse_user_dirs(content, "XDG_VIDEOS_DIR"), None);
+        assert_eq!(parse_user_dirs(content, "XDG_PICTURES_DIR"), None);
+        // $HOME relative entries end up inside the home folder
+        assert_eq!(
+            expand_path("$HOME/Downloads"),
+            home_dir().unwrap_or_default().join("Downloads")
+        );
+    }
+}
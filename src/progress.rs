@@ -0,0 +1,186 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DownloadPhase {
+    #[default]
+    Downloading,
+    // name of the yt-dlp postprocessor, e.g. ExtractAudio or EmbedThumbnail
+    PostProcessing(String),
+    Finished,
+}
+
+// one line of yt-dlp's --progress-template output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    pub phase: DownloadPhase,
+    // 0.0 to 1.0
+    pub percent: f32,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    // bytes per second
+    pub speed: Option<f64>,
+    pub eta: Option<u64>,
+}
+
+// download:%(progress)j and postprocess:%(progress)j share the status field,
+// only the postprocess one has a postprocessor
+#[derive(Deserialize)]
+struct RawProgress {
+    status: String,
+    #[serde(default)]
+    postprocessor: Option<String>,
+    #[serde(default)]
+    downloaded_bytes: Option<f64>,
+    #[serde(default)]
+    total_bytes: Option<f64>,
+    #[serde(default)]
+    total_bytes_estimate: Option<f64>,
+    #[serde(default)]
+    speed: Option<f64>,
+    #[serde(default)]
+    eta: Option<f64>,
+}
+
+impl DownloadProgress {
+    // None for lines that are no progress json (warnings, plain log lines)
+    pub fn parse(line: &str) -> Option<Self> {
+        let raw: RawProgress = serde_json::from_str(line.trim()).ok()?;
+
+        // the download itself is over once the postprocessors run
+        if let Some(postprocessor) = raw.postprocessor {
+            return Some(Self {
+                phase: DownloadPhase::PostProcessing(postprocessor),
+                percent: 1.0,
+                ..Default::default()
+            });
+        }
+
+        let downloaded_bytes = raw.downloaded_bytes.unwrap_or(0.0).max(0.0) as u64;
+        let total_bytes = raw
+            .total_bytes
+            .or(raw.total_bytes_estimate)
+            .filter(|total| *total > 0.0)
+            .map(|total| total as u64);
+        let (phase, percent) = match raw.status.as_str() {
+            "finished" => (DownloadPhase::Finished, 1.0),
+            _ => (
+                DownloadPhase::Downloading,
+                total_bytes
+                    .map(|total| (downloaded_bytes as f32 / total as f32).min(1.0))
+                    .unwrap_or(0.0),
+            ),
+        };
+
+        Some(Self {
+            phase,
+            percent,
+            downloaded_bytes,
+            total_bytes,
+            speed: raw.speed,
+            eta: raw.eta.filter(|eta| *eta >= 0.0).map(|eta| eta as u64),
+        })
+    }
+
+    // "3.4 MiB/s  ETA 0:12" or the postprocessor that is running
+    pub fn status_text(&self) -> String {
+        match &self.phase {
+            DownloadPhase::Downloading => {
+                let mut text = match self.speed {
+                    Some(speed) => format!("{}/s", format_bytes(speed as u64)),
+                    None => "-".to_string(),
+                };
+                if let Some(eta) = self.eta {
+                    text.push_str(&format!("  ETA {}:{:02}", eta / 60, eta % 60));
+                }
+                text
+            }
+            DownloadPhase::PostProcessing(postprocessor) => format!("{postprocessor} ..."),
+            DownloadPhase::Finished => "converting ...".to_string(),
+        }
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_download_lines() {
+        let line = r#"{"status": "downloading", "downloaded_bytes": 1048576, "total_bytes": 4194304, "speed": 524288.5, "eta": 6.2, "fragment_index": null}"#;
+        assert_eq!(
+            DownloadProgress::parse(line),
+            Some(DownloadProgress {
+                phase: DownloadPhase::Downloading,
+                percent: 0.25,
+                downloaded_bytes: 1048576,
+                total_bytes: Some(4194304),
+                speed: Some(524288.5),
+                eta: Some(6),
+            })
+        );
+        let finished =
+            r#"{"status": "finished", "downloaded_bytes": 4194304, "total_bytes": 4194304}"#;
+        let progress = DownloadProgress::parse(finished).unwrap();
+        assert_eq!(
+            (progress.phase, progress.percent),
+            (DownloadPhase::Finished, 1.0)
+        );
+
+        assert_eq!(DownloadProgress::parse("WARNING: unable to extract"), None);
+        assert_eq!(DownloadProgress::parse(""), None);
+    }
+
+    #[test]
+    fn reads_postprocess_lines() {
+        let line = r#"{"status": "started", "postprocessor": "ExtractAudio", "info_dict": {}}"#;
+        assert_eq!(
+            DownloadProgress::parse(line),
+            Some(DownloadProgress {
+                phase: DownloadPhase::PostProcessing("ExtractAudio".to_string()),
+                percent: 1.0,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_estimate() {
+        let line = r#"{"status": "downloading", "downloaded_bytes": 500, "total_bytes": null, "total_bytes_estimate": 1000.0}"#;
+        let progress = DownloadProgress::parse(line).unwrap();
+        assert_eq!(progress.total_bytes, Some(1000));
+        assert_eq!(progress.percent, 0.5);
+
+        // nothing known about the size leaves the bar empty
+        let line = r#"{"status": "downloading", "downloaded_bytes": 500}"#;
+        let progress = DownloadProgress::parse(line).unwrap();
+        assert_eq!((progress.total_bytes, progress.percent), (None, 0.0));
+    }
+
+    #[test]
+    fn drops_negative_or_missing_eta() {
+        for line in [
+            r#"{"status": "downloading", "eta": -3}"#,
+            r#"{"status": "downloading", "eta": null}"#,
+            r#"{"status": "downloading"}"#,
+        ] {
+            let progress = DownloadProgress::parse(line).unwrap();
+            assert_eq!(progress.eta, None, "{line}");
+            assert_eq!(progress.status_text(), "-", "{line}");
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::album::Album;
+use crate::error::AppError;
+use crate::filters::{SearchFilters, SearchOrder};
+use crate::link::{UrlTarget, playlist_url};
+use crate::media;
+use crate::youtube::YoutubeClient;
+use crate::{Id, SearchItem, SearchResponse, Snippet, ThumbnailData, Thumbnails};
+
+// every backend delivers the same SearchResponse, so the GUI does not care where the results come from
+pub trait SearchBackend {
+    fn search(
+        &self,
+        query: String,
+        max_results: i8,
+        page_token: Option<String>,
+        filters: SearchFilters,
+    ) -> impl Future<Output = Result<SearchResponse, AppError>> + Send;
+}
+
+// what the result list shows, a search or the videos of a playlist or channel
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultSource {
+    Search(String),
+    Playlist { id: String, title: String },
+    Channel { id: String, title: String },
+    // a pasted url, shown as the one item it points to
+    Url { text: String, target: UrlTarget },
+}
+
+impl Default for ResultSource {
+    fn default() -> Self {
+        ResultSource::Search(String::new())
+    }
+}
+
+impl ResultSource {
+    pub fn title(&self) -> &str {
+        match self {
+            ResultSource::Search(query) => query,
+            ResultSource::Playlist { title, .. } | ResultSource::Channel { title, .. } => title,
+            ResultSource::Url { text, .. } => text,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub enum SearchBackendKind {
+    #[default]
+    YoutubeApi,
+    YtDlp,
+}
+
+impl SearchBackendKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchBackendKind::YoutubeApi => "YouTube Data API",
+            SearchBackendKind::YtDlp => "yt-dlp (no API key)",
+        }
+    }
+
+    // yt_dlp is None while the toolchain is not found
+    pub async fn search(
+        self,
+        youtube: YoutubeClient,
+        yt_dlp: Option<PathBuf>,
+        query: String,
+        max_results: i8,
+        page_token: Option<String>,
+        filters: SearchFilters,
+    ) -> Result<SearchResponse, AppError> {
+        match self {
+            SearchBackendKind::YoutubeApi => {
+                YoutubeApiBackend::new(youtube)
+                    .search(query, max_results, page_token, filters)
+                    .await
+            }
+            SearchBackendKind::YtDlp => {
+                let yt_dlp = yt_dlp.ok_or(AppError::ToolMissing("yt-dlp"))?;
+                YtDlpBackend { yt_dlp }
+                    .search(query, max_results, page_token, filters)
+                    .await
+            }
+        }
+    }
+
+    // the entries of a playlist to download, yt-dlp lists them without an api key
+    pub async fn album(
+        self,
+        youtube: YoutubeClient,
+        yt_dlp: Option<PathBuf>,
+        playlist_id: &str,
+    ) -> Result<Album, AppError> {
+        match self {
+            SearchBackendKind::YoutubeApi => {
+                YoutubeApiBackend::new(youtube).album(playlist_id).await
+            }
+            SearchBackendKind::YtDlp => {
+                let yt_dlp = yt_dlp.ok_or(AppError::ToolMissing("yt-dlp"))?;
+                YtDlpBackend { yt_dlp }.album(playlist_id).await
+            }
+        }
+    }
+
+    // one page of a playlist's videos
+    pub async fn playlist(
+        self,
+        youtube: YoutubeClient,
+        yt_dlp: Option<PathBuf>,
+        playlist_id: &str,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> Result<SearchResponse, AppError> {
+        match self {
+            SearchBackendKind::YoutubeApi => {
+                YoutubeApiBackend::new(youtube)
+                    .playlist(playlist_id, max_results, page_token)
+                    .await
+            }
+            SearchBackendKind::YtDlp => {
+                let yt_dlp = yt_dlp.ok_or(AppError::ToolMissing("yt-dlp"))?;
+                YtDlpBackend { yt_dlp }
+                    .playlist(playlist_id, max_results, page_token)
+                    .await
+            }
+        }
+    }
+
+    // urls of other sites always go through yt-dlp, youtube ones only without the api
+    pub async fn lookup(
+        self,
+        youtube: YoutubeClient,
+        yt_dlp: Option<PathBuf>,
+        url: &str,
+        target: &UrlTarget,
+    ) -> Result<SearchResponse, AppError> {
+        if self == SearchBackendKind::YoutubeApi && !matches!(target, UrlTarget::Other(_)) {
+            return YoutubeApiBackend::new(youtube).lookup(target).await;
+        }
+        let yt_dlp = yt_dlp.ok_or(AppError::ToolMissing("yt-dlp"))?;
+        let no_playlist = matches!(target, UrlTarget::Video { .. });
+        let items = media::dump_json(&yt_dlp, url, no_playlist).await?;
+        Ok(SearchResponse {
+            kind: "yt-dlp#mediaListResponse".to_string(),
+            items: items
+                .into_iter()
+                .map(media::MediaItem::into_search_item)
+                .collect(),
+            ..Default::default()
+        })
+    }
+}
+
+pub struct YoutubeApiBackend {
+    youtube: YoutubeClient,
+}
+
+impl YoutubeApiBackend {
+    pub fn new(youtube: YoutubeClient) -> Self {
+        Self { youtube }
+    }
+
+    pub async fn playlist(
+        &self,
+        playlist_id: &str,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> Result<SearchResponse, AppError> {
+        let data = self
+            .youtube
+            .playlist_items(playlist_id, max_results, page_token.as_deref())
+            .await?;
+        self.with_durations(data).await
+    }
+
+    pub async fn channel(
+        &self,
+        channel_id: &str,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> Result<SearchResponse, AppError> {
+        let uploads = self.youtube.channel_uploads(channel_id).await?;
+        self.playlist(&uploads, max_results, page_token).await
+    }
+
+    pub async fn lookup(&self, target: &UrlTarget) -> Result<SearchResponse, AppError> {
+        match target {
+            UrlTarget::Video { id, .. } => self.youtube.video(id).await,
+            UrlTarget::Playlist(id) => self.youtube.playlist(id).await,
+            UrlTarget::Channel(channel) => self.youtube.channel(channel).await,
+            UrlTarget::Other(url) => Err(AppError::NotFound(format!("{url} on YouTube"))),
+        }
+    }
+
+    // every entry of a playlist, 50 per request is the most the api hands out
+    pub async fn album(&self, playlist_id: &str) -> Result<Album, AppError> {
+        let (title, artist) = self.youtube.playlist_info(playlist_id).await?;
+        let mut items = Vec::new();
+        let mut page_token = None;
+        loop {
+            let data = self
+                .youtube
+                .playlist_items(playlist_id, 50, page_token.as_deref())
+                .await?;
+            items.extend(data.items);
+            if data.next_page_token.is_empty() {
+                break;
+            }
+            page_token = Some(data.next_page_token);
+        }
+        Ok(Album::new(playlist_id.to_string(), title, artist, items))
+    }
+
+    // search and playlistItems know no durations, those come from the videos endpoint
+    async fn with_durations(&self, mut data: SearchResponse) -> Result<SearchResponse, AppError> {
+        let video_ids: Vec<String> = data
+            .items
+            .iter()
+            .filter_map(|item| item.id.video_id.clone())
+            .collect();
+        if !video_ids.is_empty() {
+            self.youtube
+                .set_video_durration(video_ids, &mut data)
+                .await?;
+        }
+        Ok(data)
+    }
+}
+
+impl SearchBackend for YoutubeApiBackend {
+    async fn search(
+        &self,
+        query: String,
+        max_results: i8,
+        page_token: Option<String>,
+        filters: SearchFilters,
+    ) -> Result<SearchResponse, AppError> {
+        let data = self
+            .youtube
+            .search(&query, max_results, page_token.as_deref(), &filters)
+            .await?;
+        self.with_durations(data).await
+    }
+}
+
+// yt-dlp knows no page tokens, the token is just the offset of the next page
+pub struct YtDlpBackend {
+    pub yt_dlp: PathBuf,
+}
+
+impl YtDlpBackend {
+    // --flat-playlist only lists the entries, nothing gets downloaded
+    pub async fn album(&self, playlist_id: &str) -> Result<Album, AppError> {
+        let output = tokio::process::Command::new(&self.yt_dlp)
+            .args([
+                "--flat-playlist",
+                "--dump-single-json",
+                &playlist_url(playlist_id),
+            ])
+            .output()
+            .await
+            .map_err(|error| AppError::Spawn {
+                program: "yt-dlp".to_string(),
+                message: error.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(AppError::yt_dlp(
+                output.status.code(),
+                &String::from_utf8_lossy(&output.stderr),
+            ));
+        }
+        let playlist: DlpPlaylist = serde_json::from_slice(&output.stdout)?;
+        Ok(playlist.into_album(playlist_id))
+    }
+
+    pub async fn playlist(
+        &self,
+        playlist_id: &str,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> Result<SearchResponse, AppError> {
+        let offset = page_offset(page_token)?;
+        let page_end = offset + max_results.max(1) as usize;
+        self.flat_page(
+            &playlist_url(playlist_id),
+            offset,
+            page_end,
+            "yt-dlp#playlistItemListResponse",
+        )
+        .await
+    }
+
+    // the entries offset + 1 to page_end of a search term or playlist url
+    async fn flat_page(
+        &self,
+        target: &str,
+        offset: usize,
+        page_end: usize,
+        kind: &str,
+    ) -> Result<SearchResponse, AppError> {
+        let playlist_start = (offset + 1).to_string();
+        let playlist_end = page_end.to_string();
+        let output = tokio::process::Command::new(&self.yt_dlp)
+            .args([
+                target,
+                "--dump-json",
+                "--flat-playlist",
+                "--playlist-start",
+                &playlist_start,
+                "--playlist-end",
+                &playlist_end,
+            ])
+            .output()
+            .await
+            .map_err(|error| AppError::Spawn {
+                program: "yt-dlp".to_string(),
+                message: error.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(AppError::yt_dlp(
+                output.status.code(),
+                &String::from_utf8_lossy(&output.stderr),
+            ));
+        }
+
+        // a line that is no entry is skipped
+        let items: Vec<SearchItem> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<DlpEntry>(line).ok())
+            .map(DlpEntry::into_search_item)
+            .collect();
+
+        // a full page means there are probably more results
+        let next_page_token = if offset + items.len() >= page_end {
+            page_end.to_string()
+        } else {
+            String::new()
+        };
+        Ok(SearchResponse {
+            kind: kind.to_string(),
+            next_page_token,
+            items,
+            ..Default::default()
+        })
+    }
+}
+
+impl SearchBackend for YtDlpBackend {
+    async fn search(
+        &self,
+        query: String,
+        max_results: i8,
+        page_token: Option<String>,
+        filters: SearchFilters,
+    ) -> Result<SearchResponse, AppError> {
+        let offset = page_offset(page_token)?;
+        let page_end = offset + max_results.max(1) as usize;
+        // yt-dlp knows none of the api filters, only the newest first order
+        let prefix = match filters.order {
+            SearchOrder::Date => "ytsearchdate",
+            _ => "ytsearch",
+        };
+        let search_term = format!("{prefix}{page_end}:{query}");
+        self.flat_page(&search_term, offset, page_end, "yt-dlp#searchListResponse")
+            .await
+    }
+}
+
+fn page_offset(page_token: Option<String>) -> Result<usize, AppError> {
+    match page_token {
+        Some(token) => token
+            .parse()
+            .map_err(|_| AppError::Json(format!("page token {token} is no offset"))),
+        None => Ok(0),
+    }
+}
+
+// the parts of a yt-dlp --flat-playlist entry we need
+#[derive(Deserialize)]
+struct DlpEntry {
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    duration: Option<f64>,
+    #[serde(default)]
+    channel_id: Option<String>,
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    uploader: Option<String>,
+    #[serde(default)]
+    live_status: Option<String>,
+    #[serde(default)]
+    thumbnails: Vec<DlpThumbnail>,
+}
+
+// what --dump-single-json prints for a playlist
+#[derive(Deserialize)]
+struct DlpPlaylist {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    uploader: Option<String>,
+    #[serde(default)]
+    entries: Vec<DlpEntry>,
+}
+
+impl DlpPlaylist {
+    fn into_album(self, playlist_id: &str) -> Album {
+        let artist = self.channel.or(self.uploader).unwrap_or_default();
+        let items = self
+            .entries
+            .into_iter()
+            .map(DlpEntry::into_search_item)
+            .collect();
+        Album::new(
+            playlist_id.to_string(),
+            self.title.unwrap_or_else(|| playlist_id.to_string()),
+            artist,
+            items,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct DlpThumbnail {
+    url: String,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+}
+
+impl DlpEntry {
+    fn into_search_item(self) -> SearchItem {
+        // yt-dlp lists the thumbnails in all sizes, smallest becomes default, biggest high
+        let mut thumbnails: Vec<ThumbnailData> = self
+            .thumbnails
+            .into_iter()
+            .map(|thumb| ThumbnailData {
+                url: thumb.url,
+                width: thumb.width,
+                height: thumb.height,
+            })
+            .collect();
+        thumbnails.sort_by_key(|thumb| thumb.width.unwrap_or(0));
+        let default = thumbnails.first().cloned();
+        let medium = thumbnails.get(thumbnails.len() / 2).cloned();
+        let high = thumbnails.pop();
+
+        let live_broadcast_content = match self.live_status.as_deref() {
+            Some("is_live") => "live",
+            Some("is_upcoming") => "upcoming",
+            _ => "none",
+        };
+
+        SearchItem {
+            kind: "youtube#searchResult".to_string(),
+            etag: String::new(),
+            id: Id {
+                kind: "youtube#video".to_string(),
+                video_id: Some(self.id),
+                channel_id: None,
+                playlist_id: None,
+            },
+            snippet: Snippet {
+                published_at: String::new(),
+                channel_id: self.channel_id.unwrap_or_default(),
+                title: self.title.unwrap_or_default(),
+                description: self.description.unwrap_or_default(),
+                thumbnails: Thumbnails {
+                    default,
+                    medium,
+                    high,
+                    ..Default::default()
+                },
+                channel_title: self.channel.or(self.uploader).unwrap_or_default(),
+                live_broadcast_content: live_broadcast_content.to_string(),
+            },
+            // negative, NaN or huge durations are none
+            video_durration: self
+                .duration
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
+            url: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_playlist_becomes_an_album() {
+        let json = r#"{"id": "PLabc", "title": "Mixtape", "uploader": "Some Channel", "entries": [
+            {"id": "abc123def45", "title": "First", "channel": "Band", "duration": 200.0},
+            {"id": "zzz000zzz00", "title": "[Private video]"},
+            {"id": "xyz987uvw65", "title": "Third", "channel": "Band"}
+        ]}"#;
+        let playlist: DlpPlaylist = serde_json::from_str(json).unwrap();
+        let album = playlist.into_album("PLabc");
+        assert_eq!(album.title, "Mixtape");
+        assert_eq!(album.artist, "Some Channel");
+        assert_eq!(album.track_count, 3);
+        let entries: Vec<(usize, &str)> = album
+            .entries
+            .iter()
+            .map(|entry| (entry.position, entry.video_id.as_str()))
+            .collect();
+        assert_eq!(entries, vec![(1, "abc123def45"), (3, "xyz987uvw65")]);
+    }
+}
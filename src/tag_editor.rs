@@ -0,0 +1,329 @@
+use eframe::egui::{self, Color32};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cover::{can_embed, embed_cover, read_cover, square_cover};
+use crate::error::AppError;
+use crate::events::{AppEvent, EventSender};
+use crate::tags::{FileTags, TagField, read_file_tags, write_file_tags};
+use crate::toolchain::{Tool, Toolchain};
+
+// the tags of a file and its embedded cover, as read back from disk
+pub type LoadedTags = Result<(FileTags, Option<Vec<u8>>), AppError>;
+
+struct EditedFile {
+    path: PathBuf,
+    // None while ffprobe is still reading or writing
+    tags: Option<FileTags>,
+    // shared so drawing it does not copy it every frame
+    cover: Option<Arc<[u8]>>,
+    failed: bool,
+    selected: bool,
+}
+
+impl EditedFile {
+    fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// edits the tags of one or many downloaded files, a field that is not touched keeps
+// the value of every file so a batch edit only changes what was typed
+#[derive(Default)]
+pub struct TagEditor {
+    files: Vec<EditedFile>,
+    form: FileTags,
+    // typed into since the last save
+    changed: HashSet<TagField>,
+    // the selected files disagree on these
+    mixed: HashSet<TagField>,
+    // an image file that replaces the cover of the selected files
+    cover_path: String,
+    // bumped on every reload so egui does not show a cached cover
+    generation: usize,
+}
+
+impl TagEditor {
+    pub fn open(&mut self, paths: Vec<PathBuf>, toolchain: &Toolchain, events: &EventSender) {
+        *self = Self {
+            generation: self.generation + 1,
+            ..Default::default()
+        };
+        let (Some(ffprobe), Some(ffmpeg)) = (
+            toolchain.path(Tool::Ffprobe).map(Path::to_path_buf),
+            toolchain.path(Tool::Ffmpeg).map(Path::to_path_buf),
+        ) else {
+            events.send(AppEvent::Error {
+                error: AppError::ToolMissing("ffmpeg"),
+                retry: None,
+            });
+            return;
+        };
+        for path in paths {
+            self.files.push(EditedFile {
+                path: path.clone(),
+                tags: None,
+                cover: None,
+                failed: false,
+                selected: true,
+            });
+            let events = events.clone();
+            let ffprobe = ffprobe.clone();
+            let ffmpeg = ffmpeg.clone();
+            tokio::spawn(async move {
+                let loaded = match read_file_tags(&ffprobe, &path).await {
+                    Ok(tags) => Ok((tags, read_cover(&ffmpeg, &path).await)),
+                    Err(error) => Err(error),
+                };
+                events.send(AppEvent::TagsLoaded(path, loaded));
+            });
+        }
+    }
+
+    // a file was read or written, the error is handed back for a toast
+    pub fn loaded(&mut self, path: PathBuf, loaded: LoadedTags) -> Option<AppError> {
+        let file = self.files.iter_mut().find(|file| file.path == path)?;
+        self.generation += 1;
+        match loaded {
+            Ok((tags, cover)) => {
+                file.tags = Some(tags);
+                file.cover = cover.map(Arc::from);
+                file.failed = false;
+                self.refresh_form();
+                None
+            }
+            Err(error) => {
+                file.failed = true;
+                file.selected = false;
+                self.refresh_form();
+                Some(error)
+            }
+        }
+    }
+
+    // fields that were not typed into show the common value of the selection
+    fn refresh_form(&mut self) {
+        let selected: Vec<&FileTags> = self
+            .files
+            .iter()
+            .filter(|file| file.selected)
+            .filter_map(|file| file.tags.as_ref())
+            .collect();
+        for field in TagField::ALL {
+            if self.changed.contains(&field) {
+                continue;
+            }
+            let mut values = selected.iter().map(|tags| tags.get(field));
+            let first = values.next().unwrap_or_default();
+            let mixed = values.any(|value| value != first);
+            *self.form.get_mut(field) = if mixed {
+                String::new()
+            } else {
+                first.to_string()
+            };
+            if mixed {
+                self.mixed.insert(field);
+            } else {
+                self.mixed.remove(&field);
+            }
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.files
+            .iter()
+            .any(|file| file.tags.is_none() && !file.failed)
+    }
+
+    // true when save was clicked
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        if self.files.is_empty() {
+            ui.colored_label(Color32::GRAY, "no file opened");
+            return false;
+        }
+        let mut selection_changed = false;
+        let mut save = false;
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.set_width(ui.available_width() / 2.0);
+                ui.horizontal(|ui| {
+                    let all = self.files.iter().all(|file| file.selected || file.failed);
+                    if ui
+                        .button(if all { "select none" } else { "select all" })
+                        .clicked()
+                    {
+                        for file in self.files.iter_mut().filter(|file| !file.failed) {
+                            file.selected = !all;
+                        }
+                        selection_changed = true;
+                    }
+                });
+                ui.add_space(10.0);
+                egui::ScrollArea::vertical()
+                    .id_salt("tag_editor_files")
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for file in &mut self.files {
+                            ui.horizontal(|ui| {
+                                ui.add_enabled_ui(!file.failed, |ui| {
+                                    let name = file.name();
+                                    if ui.checkbox(&mut file.selected, name).changed() {
+                                        selection_changed = true;
+                                    }
+                                });
+                                if file.failed {
+                                    ui.colored_label(Color32::RED, "failed");
+                                } else if file.tags.is_none() {
+                                    ui.spinner();
+                                }
+                            })
+                            .response
+                            .on_hover_text(file.path.to_string_lossy());
+                        }
+                    });
+            });
+
+            ui.vertical(|ui| {
+                let cover = self
+                    .files
+                    .iter()
+                    .find(|file| file.selected)
+                    .and_then(|file| file.cover.clone());
+                match cover {
+                    Some(bytes) => {
+                        ui.add(
+                            egui::Image::from_bytes(
+                                format!("bytes://tag_editor/{}.jpg", self.generation),
+                                egui::load::Bytes::Shared(bytes),
+                            )
+                            .max_size(egui::vec2(160.0, 160.0)),
+                        );
+                    }
+                    None => {
+                        ui.colored_label(Color32::GRAY, "no cover");
+                    }
+                }
+                ui.add_space(10.0);
+                egui::Grid::new("tag_editor_form")
+                    .num_columns(2)
+                    .spacing([20.0, 8.0])
+                    .show(ui, |ui| {
+                        for field in TagField::ALL {
+                            ui.label(field.label());
+                            let hint = if self.mixed.contains(&field) {
+                                "(different values)"
+                            } else {
+                                ""
+                            };
+                            let edit = ui.add(
+                                egui::TextEdit::singleline(self.form.get_mut(field))
+                                    .hint_text(hint)
+                                    .desired_width(300.0),
+                            );
+                            if edit.changed() {
+                                self.changed.insert(field);
+                            }
+                            ui.end_row();
+                        }
+                        ui.label("cover");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.cover_path)
+                                .hint_text("path of an image, empty keeps the cover")
+                                .desired_width(300.0),
+                        )
+                        .on_hover_text("mp3, m4a and flac files take a cover");
+                        ui.end_row();
+                    });
+                ui.add_space(20.0);
+                let selected = self.files.iter().filter(|file| file.selected).count();
+                let has_edits = !self.changed.is_empty() || !self.cover_path.trim().is_empty();
+                ui.horizontal(|ui| {
+                    let button = egui::Button::new(format!("save {selected} files"));
+                    save = ui
+                        .add_enabled(selected > 0 && has_edits && !self.is_busy(), button)
+                        .clicked();
+                    if !self.changed.is_empty() && ui.button("discard").clicked() {
+                        self.changed.clear();
+                        self.cover_path.clear();
+                        selection_changed = true;
+                    }
+                });
+            });
+        });
+        if selection_changed {
+            self.generation += 1;
+            self.refresh_form();
+        }
+        save
+    }
+
+    // writes the changed fields and the new cover into every selected file, each
+    // file is read again afterwards so the editor shows what is really in it
+    pub fn save(&mut self, toolchain: &Toolchain, events: &EventSender, cover_size: u32) {
+        let (Some(ffprobe), Some(ffmpeg)) = (
+            toolchain.path(Tool::Ffprobe).map(Path::to_path_buf),
+            toolchain.path(Tool::Ffmpeg).map(Path::to_path_buf),
+        ) else {
+            events.send(AppEvent::Error {
+                error: AppError::ToolMissing("ffmpeg"),
+                retry: None,
+            });
+            return;
+        };
+        let cover_path = self.cover_path.trim();
+        let cover = if cover_path.is_empty() {
+            None
+        } else {
+            match std::fs::read(cover_path) {
+                Ok(bytes) => Some(bytes),
+                Err(error) => {
+                    events.send(AppEvent::Error {
+                        error: error.into(),
+                        retry: None,
+                    });
+                    return;
+                }
+            }
+        };
+
+        for file in self.files.iter_mut().filter(|file| file.selected) {
+            let Some(mut tags) = file.tags.take() else {
+                continue;
+            };
+            for field in &self.changed {
+                *tags.get_mut(*field) = self.form.get(*field).to_string();
+            }
+            let path = file.path.clone();
+            let cover = cover.clone().filter(|_| can_embed(&path));
+            let events = events.clone();
+            let ffprobe = ffprobe.clone();
+            let ffmpeg = ffmpeg.clone();
+            tokio::spawn(async move {
+                let written = async {
+                    write_file_tags(&ffmpeg, &path, &tags).await?;
+                    if let Some(original) = cover {
+                        let jpeg = tokio::task::spawn_blocking(move || {
+                            square_cover(&original, cover_size)
+                        })
+                        .await
+                        .map_err(|error| AppError::Image(error.to_string()))??;
+                        embed_cover(&ffmpeg, &path, &jpeg, None).await?;
+                    }
+                    read_file_tags(&ffprobe, &path).await
+                };
+                let loaded = match written.await {
+                    Ok(tags) => Ok((tags, read_cover(&ffmpeg, &path).await)),
+                    Err(error) => Err(error),
+                };
+                events.send(AppEvent::TagsLoaded(path, loaded));
+            });
+        }
+        self.changed.clear();
+        self.cover_path.clear();
+    }
+}
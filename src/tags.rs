@@ -0,0 +1,500 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::album::AlbumTrack;
+use crate::error::AppError;
+use crate::ffmpeg;
+
+// words that only describe the upload, a bracket or trailing part made of nothing else is dropped
+const NOISE_WORDS: [&str; 19] = [
+    "official",
+    "video",
+    "music",
+    "audio",
+    "lyric",
+    "lyrics",
+    "visualizer",
+    "visualiser",
+    "hd",
+    "hq",
+    "4k",
+    "1080p",
+    "720p",
+    "mv",
+    "m/v",
+    "clip",
+    "officiel",
+    "oficial",
+    "explicit",
+];
+
+// may join noise words inside a bracket, "(Video with Lyrics)", but are no noise alone
+const CONNECTORS: [&str; 5] = ["with", "and", "&", "+", "/"];
+
+// channel name endings that are no part of the artist
+const CHANNEL_SUFFIXES: [&str; 4] = [" - Topic", "VEVO", " Official", " Music"];
+
+// the tags written into a finished download
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackTags {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    // number and total
+    pub track: Option<(usize, usize)>,
+}
+
+impl TrackTags {
+    // "Artist - Song (Official Video) [HD]" by "ArtistVEVO" becomes Artist / Song
+    pub fn parse(video_title: &str, channel: &str) -> Self {
+        let decoded = decode_entities(video_title);
+        // en and em dashes separate just like the plain one
+        let cleaned = strip_brackets(&decoded)
+            .replace(" – ", " - ")
+            .replace(" — ", " - ");
+        // a trailing "| Official Audio" goes as long as something is left, a trailing
+        // "- Lyric Video" only next to artist and title so "India.Arie - Video" stays
+        let mut sections: Vec<&str> = cleaned
+            .split(" | ")
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        while sections.len() > 1 && sections.last().is_some_and(|part| is_noise(part)) {
+            sections.pop();
+        }
+        let mut parts: Vec<&str> = sections
+            .into_iter()
+            .flat_map(|section| section.split(" - "))
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        while parts.len() > 2 && parts.last().is_some_and(|part| is_noise(part)) {
+            parts.pop();
+        }
+        let (artist, title) = match parts.as_slice() {
+            [] => (clean_channel(channel), decoded.trim().to_string()),
+            [title] => (clean_channel(channel), title.to_string()),
+            [artist, rest @ ..] => (artist.to_string(), rest.join(" - ")),
+        };
+        Self {
+            title,
+            artist,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_album(mut self, track: &AlbumTrack) -> Self {
+        self.album = Some(track.album.clone());
+        self.album_artist = Some(track.album_artist.clone());
+        self.track = Some((track.track, track.track_count));
+        self
+    }
+
+    // short form for the result row
+    pub fn preview(&self) -> String {
+        format!("{} — {}", self.artist, self.title)
+    }
+
+    // ffmpeg maps these keys to ID3 frames, MP4 atoms or Vorbis comments by itself
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![
+            ("title", self.title.clone()),
+            ("artist", self.artist.clone()),
+        ];
+        if let Some(album) = &self.album {
+            pairs.push(("album", album.clone()));
+        }
+        if let Some(album_artist) = &self.album_artist {
+            pairs.push(("album_artist", album_artist.clone()));
+        }
+        if let Some((track, count)) = self.track {
+            pairs.push(("track", format!("{track}/{count}")));
+        }
+        pairs
+    }
+}
+
+// what the tag editor shows and writes, an empty field is no tag
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FileTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub track: String,
+    pub year: String,
+    pub genre: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    Track,
+    Year,
+    Genre,
+}
+
+impl TagField {
+    pub const ALL: [TagField; 6] = [
+        TagField::Title,
+        TagField::Artist,
+        TagField::Album,
+        TagField::Track,
+        TagField::Year,
+        TagField::Genre,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TagField::Title => "title",
+            TagField::Artist => "artist",
+            TagField::Album => "album",
+            TagField::Track => "track",
+            TagField::Year => "year",
+            TagField::Genre => "genre",
+        }
+    }
+
+    // the ffmpeg name, the first one is written, all of them are read
+    fn keys(&self) -> &'static [&'static str] {
+        match self {
+            TagField::Title => &["title"],
+            TagField::Artist => &["artist"],
+            TagField::Album => &["album"],
+            TagField::Track => &["track", "tracknumber"],
+            TagField::Year => &["date", "year"],
+            TagField::Genre => &["genre"],
+        }
+    }
+}
+
+impl FileTags {
+    pub fn get(&self, field: TagField) -> &str {
+        match field {
+            TagField::Title => &self.title,
+            TagField::Artist => &self.artist,
+            TagField::Album => &self.album,
+            TagField::Track => &self.track,
+            TagField::Year => &self.year,
+            TagField::Genre => &self.genre,
+        }
+    }
+
+    pub fn get_mut(&mut self, field: TagField) -> &mut String {
+        match field {
+            TagField::Title => &mut self.title,
+            TagField::Artist => &mut self.artist,
+            TagField::Album => &mut self.album,
+            TagField::Track => &mut self.track,
+            TagField::Year => &mut self.year,
+            TagField::Genre => &mut self.genre,
+        }
+    }
+
+    // ffprobe -show_entries format_tags:stream_tags -of json, ogg and opus keep their
+    // tags on the audio stream and vorbis comments are often upper case
+    pub fn from_probe(probe: &serde_json::Value) -> Self {
+        let mut sources = vec![probe.pointer("/format/tags")];
+        if let Some(streams) = probe.get("streams").and_then(|streams| streams.as_array()) {
+            sources.extend(streams.iter().map(|stream| stream.get("tags")));
+        }
+        let lookup = |field: TagField| {
+            sources
+                .iter()
+                .flatten()
+                .filter_map(|tags| tags.as_object())
+                .flat_map(|tags| tags.iter())
+                .find(|(key, _)| {
+                    field
+                        .keys()
+                        .iter()
+                        .any(|known| key.eq_ignore_ascii_case(known))
+                })
+                .and_then(|(_, value)| value.as_str())
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let mut tags = FileTags::default();
+        for field in TagField::ALL {
+            *tags.get_mut(field) = lookup(field);
+        }
+        tags
+    }
+
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        TagField::ALL
+            .iter()
+            .map(|field| (field.keys()[0], self.get(*field).trim().to_string()))
+            .collect()
+    }
+}
+
+pub async fn read_file_tags(ffprobe: &Path, path: &Path) -> Result<FileTags, AppError> {
+    probe_file(ffprobe, path).await.map(|(tags, _)| tags)
+}
+
+// the tags and the length in seconds
+pub async fn probe_file(ffprobe: &Path, path: &Path) -> Result<(FileTags, Option<f64>), AppError> {
+    let args = [
+        "-v",
+        "error",
+        "-show_entries",
+        "format=duration:format_tags:stream_tags",
+        "-of",
+        "json",
+    ]
+    .map(String::from)
+    .into_iter()
+    .chain([path.to_string_lossy().into_owned()])
+    .collect::<Vec<_>>();
+    let stdout = ffmpeg::run(ffprobe, &args).await?;
+    let probe: serde_json::Value = serde_json::from_slice(&stdout)?;
+    // ffprobe prints numbers as strings
+    let duration = probe
+        .pointer("/format/duration")
+        .and_then(|duration| duration.as_str())
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0);
+    Ok((FileTags::from_probe(&probe), duration))
+}
+
+pub async fn write_tags(ffmpeg: &Path, path: &Path, tags: &TrackTags) -> Result<(), AppError> {
+    write_metadata(ffmpeg, path, &tags.pairs()).await
+}
+
+// an empty value removes the tag
+pub async fn write_file_tags(ffmpeg: &Path, path: &Path, tags: &FileTags) -> Result<(), AppError> {
+    write_metadata(ffmpeg, path, &tags.pairs()).await
+}
+
+// copies every stream (the embedded cover too) and only touches the tags
+pub async fn write_metadata(
+    ffmpeg: &Path,
+    path: &Path,
+    pairs: &[(&str, String)],
+) -> Result<(), AppError> {
+    let mut args = ["-map", "0", "-c", "copy", "-map_metadata", "0"]
+        .map(String::from)
+        .to_vec();
+    // windows explorer and most car radios only read ID3v2.3
+    if has_extension(path, &["mp3"]) {
+        args.extend(["-id3v2_version", "3"].map(String::from));
+    }
+    // the ogg muxer prefers the old stream comments over new global ones
+    let on_stream = has_extension(path, &["ogg", "oga", "opus"]);
+    for (key, value) in pairs {
+        args.push("-metadata".to_string());
+        args.push(format!("{key}={value}"));
+        if on_stream {
+            args.push("-metadata:s:a:0".to_string());
+            args.push(format!("{key}={value}"));
+        }
+    }
+    ffmpeg::rewrite(ffmpeg, path, args).await
+}
+
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
+
+// a bracket or trailing part made of nothing else, "(Video with Lyrics)" or "- Lyric Video"
+fn is_noise(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    words.iter().any(|word| NOISE_WORDS.contains(word))
+        && words
+            .iter()
+            .all(|word| NOISE_WORDS.contains(word) || CONNECTORS.contains(word))
+}
+
+// (Official Video), [HD] and 【MV】 go, (feat. X) and (Live) stay
+fn strip_brackets(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['(', '[', '【']) {
+        let open = rest[start..].chars().next().unwrap_or('(');
+        let close = match open {
+            '(' => ')',
+            '[' => ']',
+            _ => '】',
+        };
+        let inner_start = start + open.len_utf8();
+        let Some(length) = rest[inner_start..].find(close) else {
+            break;
+        };
+        let inner = &rest[inner_start..inner_start + length];
+        let end = inner_start + length + close.len_utf8();
+        result.push_str(&rest[..start]);
+        if !is_noise(inner) {
+            result.push_str(&rest[start..end]);
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn clean_channel(channel: &str) -> String {
+    let mut artist = channel.trim();
+    for suffix in CHANNEL_SUFFIXES {
+        if let Some(stripped) = artist.strip_suffix(suffix)
+            && !stripped.trim().is_empty()
+        {
+            artist = stripped.trim();
+        }
+    }
+    artist.to_string()
+}
+
+// the Data API hands out titles with &amp;, &quot; and &#39; in them
+pub fn decode_entities(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        let entity = &rest[start..];
+        let decoded = entity.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let name = &entity[1..end];
+            let c = match name {
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| name.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end + 1))
+        });
+        match decoded {
+            Some((c, length)) => {
+                result.push(c);
+                rest = &entity[length..];
+            }
+            None => {
+                result.push('&');
+                rest = &entity[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_and_cleans_titles() {
+        let samples = [
+            (
+                "Artist - Song (Official Video) [HD]",
+                "ArtistVEVO",
+                "Artist",
+                "Song",
+            ),
+            ("Song", "ArtistVEVO", "Artist", "Song"),
+            (
+                "Song (feat. Other)",
+                "Artist - Topic",
+                "Artist",
+                "Song (feat. Other)",
+            ),
+            (
+                "Guns N&#39; Roses - Sweet Child O&#39; Mine (Official Music Video)",
+                "Guns N' Roses",
+                "Guns N' Roses",
+                "Sweet Child O' Mine",
+            ),
+            (
+                "Simon &amp; Garfunkel – The Boxer (Live) | Official Audio",
+                "Simon & Garfunkel",
+                "Simon & Garfunkel",
+                "The Boxer (Live)",
+            ),
+            (
+                "【MV】 Artist - Song [Lyric Video]",
+                "Label",
+                "Artist",
+                "Song",
+            ),
+            (
+                "Artist - Song - Official Music Video",
+                "Label",
+                "Artist",
+                "Song",
+            ),
+            ("Song | Official Audio", "Artist - Topic", "Artist", "Song"),
+            ("India.Arie - Video", "IndiaArieVEVO", "India.Arie", "Video"),
+            (
+                "Artist - Song (Video with Lyrics)",
+                "Artist",
+                "Artist",
+                "Song",
+            ),
+            (
+                "Song (with Friend)",
+                "Artist",
+                "Artist",
+                "Song (with Friend)",
+            ),
+            (
+                "Official Video",
+                "Artist Official",
+                "Artist",
+                "Official Video",
+            ),
+        ];
+        for (title, channel, artist, song) in samples {
+            let tags = TrackTags::parse(title, channel);
+            assert_eq!(
+                (tags.artist.as_str(), tags.title.as_str()),
+                (artist, song),
+                "{title}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_probe_tags_of_any_case() {
+        let probe = serde_json::json!({
+            "streams": [{"tags": {"TITLE": "Song", "ARTIST": "Band", "TRACKNUMBER": "3"}}],
+            "format": {"tags": {"album": "Album", "DATE": "2021", "encoder": "Lavf60"}}
+        });
+        assert_eq!(
+            FileTags::from_probe(&probe),
+            FileTags {
+                title: "Song".to_string(),
+                artist: "Band".to_string(),
+                album: "Album".to_string(),
+                track: "3".to_string(),
+                year: "2021".to_string(),
+                genre: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_html_entities() {
+        assert_eq!(
+            decode_entities("&quot;A&quot; &lt;3 &#x1F3B5; &#233; &unknown; AT&T"),
+            "\"A\" <3 🎵 é &unknown; AT&T"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use eframe::egui;
+
+use crate::error::AppError;
+use crate::search::ResultSource;
+
+// what the retry button of a toast starts again
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryAction {
+    Fetch {
+        source: ResultSource,
+        page_token: Option<String>,
+    },
+    Album {
+        playlist_id: String,
+    },
+}
+
+struct Toast {
+    id: usize,
+    error: AppError,
+    retry: Option<RetryAction>,
+}
+
+// errors that belong to no result row, stacked in the bottom right corner until dismissed
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    next_id: usize,
+}
+
+impl Toasts {
+    pub fn push(&mut self, error: AppError, retry: Option<RetryAction>) {
+        // the same error twice in a row only shows once
+        if self.toasts.last().is_some_and(|toast| toast.error == error) {
+            return;
+        }
+        self.next_id += 1;
+        self.toasts.push(Toast {
+            id: self.next_id,
+            error,
+            retry,
+        });
+    }
+
+    // returns the action of a clicked retry button, that toast is gone afterwards
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<RetryAction> {
+        if self.toasts.is_empty() {
+            return None;
+        }
+        let mut dismiss = None;
+        let mut retry = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-20.0, -20.0))
+            .show(ctx, |ui| {
+                for toast in &self.toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(450.0);
+                        toast.error.show(ui);
+                        ui.horizontal(|ui| {
+                            if let Some(action) = &toast.retry
+                                && ui.button("retry").clicked()
+                            {
+                                retry = Some(action.clone());
+                                dismiss = Some(toast.id);
+                            }
+                            if ui.button("dismiss").clicked() {
+                                dismiss = Some(toast.id);
+                            }
+                        });
+                    });
+                    ui.add_space(10.0);
+                }
+            });
+        if let Some(id) = dismiss {
+            self.toasts.retain(|toast| toast.id != id);
+        }
+        retry
+    }
+}
@@ -0,0 +1,408 @@
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::platform::expand_path;
+
+// target/debug/<app> is three levels below the project root, so cargo run finds the bundled folders too
+const EXE_SEARCH_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    YtDlp,
+    Ffmpeg,
+    Ffprobe,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 3] = [Tool::YtDlp, Tool::Ffmpeg, Tool::Ffprobe];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tool::YtDlp => "yt-dlp",
+            Tool::Ffmpeg => "ffmpeg",
+            Tool::Ffprobe => "ffprobe",
+        }
+    }
+
+    fn version_arg(&self) -> &'static str {
+        match self {
+            Tool::YtDlp => "--version",
+            Tool::Ffmpeg | Tool::Ffprobe => "-version",
+        }
+    }
+
+    // the layout of the download folders from the readme
+    fn bundled_path(&self) -> PathBuf {
+        match self {
+            Tool::YtDlp => Path::new("yt_dlp").join(YT_DLP_BUNDLED),
+            Tool::Ffmpeg => Path::new("ffmpeg").join(format!("ffmpeg{}", env::consts::EXE_SUFFIX)),
+            Tool::Ffprobe => {
+                Path::new("ffmpeg").join(format!("ffprobe{}", env::consts::EXE_SUFFIX))
+            }
+        }
+    }
+
+    fn binary_name(&self) -> String {
+        format!("{}{}", self.label(), env::consts::EXE_SUFFIX)
+    }
+}
+
+#[cfg(target_os = "windows")]
+const YT_DLP_BUNDLED: &str = "yt-dlp.exe";
+
+#[cfg(target_os = "macos")]
+const YT_DLP_BUNDLED: &str = "yt-dlp_macos";
+
+#[cfg(target_os = "linux")]
+const YT_DLP_BUNDLED: &str = "yt-dlp_linux";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToolSource {
+    Settings,
+    NextToApp,
+    SystemPath,
+}
+
+impl ToolSource {
+    fn label(&self) -> &'static str {
+        match self {
+            ToolSource::Settings => "from settings",
+            ToolSource::NextToApp => "next to the app",
+            ToolSource::SystemPath => "from PATH",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ToolStatus {
+    #[default]
+    Checking,
+    Found {
+        path: PathBuf,
+        source: ToolSource,
+        version: String,
+    },
+    // the file is there but does not answer to --version
+    Broken {
+        path: PathBuf,
+        error: String,
+    },
+    Missing,
+}
+
+// paths the user typed in, empty means search for it
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ToolPaths {
+    pub yt_dlp: String,
+    pub ffmpeg: String,
+    pub ffprobe: String,
+}
+
+impl ToolPaths {
+    fn get(&self, tool: Tool) -> &str {
+        match tool {
+            Tool::YtDlp => &self.yt_dlp,
+            Tool::Ffmpeg => &self.ffmpeg,
+            Tool::Ffprobe => &self.ffprobe,
+        }
+    }
+
+    fn get_mut(&mut self, tool: Tool) -> &mut String {
+        match tool {
+            Tool::YtDlp => &mut self.yt_dlp,
+            Tool::Ffmpeg => &mut self.ffmpeg,
+            Tool::Ffprobe => &mut self.ffprobe,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Toolchain {
+    yt_dlp: ToolStatus,
+    ffmpeg: ToolStatus,
+    ffprobe: ToolStatus,
+}
+
+impl Toolchain {
+    // blocking, every binary found gets started once with its version flag
+    pub fn locate(paths: &ToolPaths) -> Self {
+        Self {
+            yt_dlp: locate_tool(Tool::YtDlp, paths.get(Tool::YtDlp)),
+            ffmpeg: locate_tool(Tool::Ffmpeg, paths.get(Tool::Ffmpeg)),
+            ffprobe: locate_tool(Tool::Ffprobe, paths.get(Tool::Ffprobe)),
+        }
+    }
+
+    pub fn status(&self, tool: Tool) -> &ToolStatus {
+        match tool {
+            Tool::YtDlp => &self.yt_dlp,
+            Tool::Ffmpeg => &self.ffmpeg,
+            Tool::Ffprobe => &self.ffprobe,
+        }
+    }
+
+    // only binaries that passed the version check
+    pub fn path(&self, tool: Tool) -> Option<&Path> {
+        match self.status(tool) {
+            ToolStatus::Found { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    // settings widget, returns true when the paths should be checked again
+    pub fn show(&self, ui: &mut egui::Ui, paths: &mut ToolPaths) -> bool {
+        let mut check_again = false;
+        egui::Grid::new("toolchain")
+            .num_columns(3)
+            .spacing([20.0, 10.0])
+            .show(ui, |ui| {
+                for tool in Tool::ALL {
+                    ui.label(format!("{}:", tool.label()));
+                    let path_field = ui.add(
+                        egui::TextEdit::singleline(paths.get_mut(tool))
+                            .hint_text("search automatically")
+                            .desired_width(300.0),
+                    );
+                    if path_field.lost_focus() {
+                        check_again = true;
+                    }
+                    match self.status(tool) {
+                        ToolStatus::Checking => {
+                            ui.add(egui::Spinner::new());
+                        }
+                        ToolStatus::Found {
+                            path,
+                            source,
+                            version,
+                        } => {
+                            ui.colored_label(
+                                Color32::DARK_GREEN,
+                                format!("✔ {version} ({})", source.label()),
+                            )
+                            .on_hover_text(path.to_string_lossy());
+                        }
+                        ToolStatus::Broken { path, error } => {
+                            ui.colored_label(Color32::RED, "✖ does not start")
+                                .on_hover_text(format!("{}\n{error}", path.to_string_lossy()));
+                        }
+                        ToolStatus::Missing => {
+                            ui.colored_label(Color32::RED, "✖ not found");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        if ui.button("check again").clicked() {
+            check_again = true;
+        }
+        check_again
+    }
+}
+
+// settings path first, then next to the executable, then $PATH
+fn locate_tool(tool: Tool, explicit: &str) -> ToolStatus {
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let path_var = env::var_os("PATH");
+    first_working(
+        tool,
+        candidates(tool, explicit, exe_dir.as_deref(), path_var.as_deref()),
+    )
+}
+
+fn candidates(
+    tool: Tool,
+    explicit: &str,
+    exe_dir: Option<&Path>,
+    path_var: Option<&OsStr>,
+) -> Vec<(PathBuf, ToolSource)> {
+    let mut candidates = Vec::new();
+    if !explicit.trim().is_empty() {
+        candidates.push((expand_path(explicit), ToolSource::Settings));
+    }
+    if let Some(exe_dir) = exe_dir {
+        for dir in exe_dir.ancestors().take(EXE_SEARCH_DEPTH) {
+            candidates.push((dir.join(tool.bundled_path()), ToolSource::NextToApp));
+            candidates.push((dir.join(tool.binary_name()), ToolSource::NextToApp));
+        }
+    }
+    if let Some(path_var) = path_var {
+        for dir in env::split_paths(path_var) {
+            candidates.push((dir.join(tool.binary_name()), ToolSource::SystemPath));
+        }
+    }
+    candidates
+}
+
+// a broken binary is only reported if there is no working one further down
+fn first_working(tool: Tool, candidates: Vec<(PathBuf, ToolSource)>) -> ToolStatus {
+    let mut status = ToolStatus::Missing;
+    for (path, source) in candidates {
+        if !path.is_file() {
+            continue;
+        }
+        match read_version(tool, &path) {
+            Ok(version) => {
+                return ToolStatus::Found {
+                    path,
+                    source,
+                    version,
+                };
+            }
+            Err(error) => {
+                if status == ToolStatus::Missing {
+                    status = ToolStatus::Broken { path, error };
+                }
+            }
+        }
+    }
+    status
+}
+
+fn read_version(tool: Tool, path: &Path) -> Result<String, String> {
+    let output = Command::new(path)
+        .arg(tool.version_arg())
+        .output()
+        .map_err(|error| error.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} {}: {}",
+            tool.version_arg(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_version(tool, &stdout).ok_or_else(|| "no version in the output".to_string())
+}
+
+// yt-dlp prints just "2024.08.06", ffmpeg starts with "ffmpeg version 7.0.1 Copyright ..."
+fn parse_version(tool: Tool, output: &str) -> Option<String> {
+    let first_line = output.lines().next()?.trim();
+    let version = match tool {
+        Tool::YtDlp => first_line,
+        Tool::Ffmpeg | Tool::Ffprobe => first_line
+            .strip_prefix(tool.label())?
+            .trim_start()
+            .strip_prefix("version")?
+            .split_whitespace()
+            .next()?,
+    };
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_versions() {
+        assert_eq!(
+            parse_version(Tool::YtDlp, "2024.08.06\n").as_deref(),
+            Some("2024.08.06")
+        );
+        assert_eq!(
+            parse_version(
+                Tool::Ffmpeg,
+                "ffmpeg version 7.0.1 Copyright (c) 2000-2024 the FFmpeg developers\nbuilt with gcc"
+            )
+            .as_deref(),
+            Some("7.0.1")
+        );
+        assert_eq!(
+            parse_version(Tool::Ffprobe, "ffprobe version n6.1-3-g5f3b Copyright").as_deref(),
+            Some("n6.1-3-g5f3b")
+        );
+        // ffprobe output is no ffmpeg version and an empty answer is none at all
+        assert_eq!(parse_version(Tool::Ffmpeg, "ffprobe version 7.0.1"), None);
+        assert_eq!(parse_version(Tool::Ffmpeg, "ffmpeg 7.0.1"), None);
+        assert_eq!(parse_version(Tool::YtDlp, ""), None);
+        assert_eq!(parse_version(Tool::YtDlp, "\n"), None);
+    }
+
+    #[test]
+    fn looks_in_settings_then_app_then_path() {
+        let exe_dir = Path::new("/opt/app/target/debug");
+        let path_var = env::join_paths(["/usr/local/bin", "/usr/bin"]).unwrap();
+        let found = candidates(Tool::Ffmpeg, "/srv/ffmpeg", Some(exe_dir), Some(&path_var));
+        let ffmpeg = tool_file("ffmpeg");
+        let bundled = Path::new("ffmpeg").join(&ffmpeg);
+        let mut expected = vec![(PathBuf::from("/srv/ffmpeg"), ToolSource::Settings)];
+        for dir in [
+            "/opt/app/target/debug",
+            "/opt/app/target",
+            "/opt/app",
+            "/opt",
+        ] {
+            expected.push((Path::new(dir).join(&bundled), ToolSource::NextToApp));
+            expected.push((Path::new(dir).join(&ffmpeg), ToolSource::NextToApp));
+        }
+        for dir in ["/usr/local/bin", "/usr/bin"] {
+            expected.push((Path::new(dir).join(&ffmpeg), ToolSource::SystemPath));
+        }
+        assert_eq!(found, expected);
+
+        // an empty settings path is skipped
+        let found = candidates(Tool::Ffmpeg, "  ", None, Some(&path_var));
+        assert_eq!(found[0].1, ToolSource::SystemPath);
+    }
+
+    fn tool_file(name: &str) -> String {
+        format!("{name}{}", env::consts::EXE_SUFFIX)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn takes_the_first_working_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("toolchain_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = |name: &str, body: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+        let broken = script("broken", "exit 1");
+        let working = script("working", "echo 2024.08.06");
+        let missing = dir.join("missing");
+
+        let status = first_working(
+            Tool::YtDlp,
+            vec![
+                (missing.clone(), ToolSource::Settings),
+                (broken.clone(), ToolSource::NextToApp),
+                (working.clone(), ToolSource::SystemPath),
+            ],
+        );
+        assert_eq!(
+            status,
+            ToolStatus::Found {
+                path: working,
+                source: ToolSource::SystemPath,
+                version: "2024.08.06".to_string(),
+            }
+        );
+        // a broken binary is reported when nothing works
+        let status = first_working(
+            Tool::YtDlp,
+            vec![
+                (missing.clone(), ToolSource::Settings),
+                (broken.clone(), ToolSource::NextToApp),
+            ],
+        );
+        assert!(matches!(status, ToolStatus::Broken { path, .. } if path == broken));
+        assert_eq!(
+            first_working(Tool::YtDlp, vec![(missing, ToolSource::Settings)]),
+            ToolStatus::Missing
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
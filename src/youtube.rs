@@ -0,0 +1,602 @@
+use reqwest::Client;
+use serde::Deserialize;
+use std::env;
+
+use crate::duration::parse_iso8601_duration;
+use crate::error::AppError;
+use crate::filters::SearchFilters;
+use crate::link::ChannelRef;
+use crate::{Id, SearchItem, SearchResponse, Snippet, Thumbnails};
+
+pub const YT_API_BASE_URL: &str = "https://www.googleapis.com/youtube/v3";
+
+// one client for all Data API calls, reqwest::Client is an Arc inside so cloning is cheap
+#[derive(Clone)]
+pub struct YoutubeClient {
+    base_url: String,
+    api_key: String,
+    client: Client,
+}
+
+impl Default for YoutubeClient {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl YoutubeClient {
+    pub fn new(base_url: impl Into<String>, api_key: impl Into<String>, client: Client) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: api_key.into(),
+            client,
+        }
+    }
+
+    // YT_API holds the key, YT_API_BASE_URL can point to another server (e.g. a local stub)
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("YT_API_BASE_URL").unwrap_or_else(|_| YT_API_BASE_URL.to_string()),
+            env::var("YT_API").unwrap_or_default(),
+            Client::new(),
+        )
+    }
+
+    async fn get(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<reqwest::Response, AppError> {
+        if self.api_key.is_empty() {
+            return Err(AppError::MissingApiKey);
+        }
+        let response = self
+            .client
+            .get(format!("{}/{endpoint}", self.base_url))
+            .query(query)
+            .query(&[("key", self.api_key.as_str())])
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(api_error(status.as_u16(), &body));
+        }
+        Ok(response)
+    }
+
+    pub async fn search(
+        &self,
+        query: &str,
+        max_results: i8,
+        page_token: Option<&str>,
+        filters: &SearchFilters,
+    ) -> Result<SearchResponse, AppError> {
+        let max_results = max_results.to_string();
+        let filter_params = filters.query_params(SearchFilters::unix_now());
+        let mut params = vec![
+            ("part", "snippet"),
+            ("q", query),
+            ("maxResults", max_results.as_str()),
+        ];
+        params.extend(
+            filter_params
+                .iter()
+                .map(|(key, value)| (*key, value.as_str())),
+        );
+        if let Some(page_token) = page_token {
+            params.push(("pageToken", page_token));
+        }
+
+        let response = self.get("search", &params).await?;
+        let data: SearchResponse = response.json::<SearchResponse>().await?;
+        Ok(data)
+    }
+
+    // the videos of a playlist, turned into search items so the result list can show them
+    pub async fn playlist_items(
+        &self,
+        playlist_id: &str,
+        max_results: i8,
+        page_token: Option<&str>,
+    ) -> Result<SearchResponse, AppError> {
+        let max_results = max_results.to_string();
+        let mut params = vec![
+            ("part", "snippet"),
+            ("playlistId", playlist_id),
+            ("maxResults", max_results.as_str()),
+        ];
+        if let Some(page_token) = page_token {
+            params.push(("pageToken", page_token));
+        }
+
+        let response = self.get("playlistItems", &params).await?;
+        let data: PlaylistItemsResponse = response.json().await?;
+        Ok(SearchResponse {
+            kind: data.kind,
+            etag: data.etag,
+            next_page_token: data.next_page_token,
+            items: data
+                .items
+                .into_iter()
+                .map(PlaylistItem::into_search_item)
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    // title and owner of a playlist
+    pub async fn playlist_info(&self, playlist_id: &str) -> Result<(String, String), AppError> {
+        let item = self.playlist(playlist_id).await?.items.remove(0);
+        Ok((item.snippet.title, item.snippet.channel_title))
+    }
+
+    // the lookups of a pasted url, each one answers with exactly one item
+    pub async fn video(&self, video_id: &str) -> Result<SearchResponse, AppError> {
+        self.resource("videos", "snippet,contentDetails", ("id", video_id))
+            .await
+    }
+
+    pub async fn playlist(&self, playlist_id: &str) -> Result<SearchResponse, AppError> {
+        self.resource("playlists", "snippet", ("id", playlist_id))
+            .await
+    }
+
+    pub async fn channel(&self, channel: &ChannelRef) -> Result<SearchResponse, AppError> {
+        let filter = match channel {
+            ChannelRef::Id(id) => ("id", id.as_str()),
+            ChannelRef::Handle(handle) => ("forHandle", handle.as_str()),
+            ChannelRef::User(name) => ("forUsername", name.as_str()),
+        };
+        self.resource("channels", "snippet", filter).await
+    }
+
+    async fn resource(
+        &self,
+        endpoint: &str,
+        part: &str,
+        filter: (&str, &str),
+    ) -> Result<SearchResponse, AppError> {
+        let response = self.get(endpoint, &[("part", part), filter]).await?;
+        let data: ResourceListResponse = response.json().await?;
+        let Some(resource) = data.items.into_iter().next() else {
+            return Err(AppError::NotFound(format!(
+                "{} {}",
+                endpoint.trim_end_matches('s'),
+                filter.1
+            )));
+        };
+        Ok(SearchResponse {
+            items: vec![resource.into_search_item()],
+            ..Default::default()
+        })
+    }
+
+    // every channel has a hidden playlist with all of its uploads
+    pub async fn channel_uploads(&self, channel_id: &str) -> Result<String, AppError> {
+        let response = self
+            .get(
+                "channels",
+                &[("part", "contentDetails"), ("id", channel_id)],
+            )
+            .await?;
+        let data: serde_json::Value = response.json().await?;
+        data.pointer("/items/0/contentDetails/relatedPlaylists/uploads")
+            .and_then(|uploads| uploads.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| AppError::Json(format!("channel {channel_id} has no uploads playlist")))
+    }
+
+    pub async fn set_video_durration(
+        &self,
+        video_id: Vec<String>,
+        meta_data: &mut SearchResponse,
+    ) -> Result<(), AppError> {
+        let final_string = video_id.join(",");
+        let response = self
+            .get(
+                "videos",
+                &[("part", "contentDetails"), ("id", final_string.as_str())],
+            )
+            .await?;
+        let data: serde_json::Value = response.json::<serde_json::Value>().await?;
+        if let Some(items) = data.get("items").and_then(|v| v.as_array()) {
+            for item in items {
+                if let (Some(video_id), Some(duration)) = (
+                    item.get("id").and_then(|v| v.as_str()),
+                    item.get("contentDetails")
+                        .and_then(|cd| cd.get("duration"))
+                        .and_then(|d| d.as_str()),
+                ) {
+                    // a duration we can not read just stays unknown
+                    let Some(parsed_duration) = parse_iso8601_duration(duration) else {
+                        continue;
+                    };
+                    for item in meta_data.items.iter_mut() {
+                        if item.id.video_id.as_deref() == Some(video_id) {
+                            item.video_durration = Some(parsed_duration);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct PlaylistItemsResponse {
+    #[serde(default)]
+    kind: String,
+    #[serde(default)]
+    etag: String,
+    #[serde(rename = "nextPageToken", default)]
+    next_page_token: String,
+    #[serde(default)]
+    items: Vec<PlaylistItem>,
+}
+
+#[derive(Deserialize)]
+struct PlaylistItem {
+    kind: String,
+    etag: String,
+    snippet: PlaylistItemSnippet,
+}
+
+#[derive(Deserialize)]
+struct PlaylistItemSnippet {
+    #[serde(rename = "publishedAt", default)]
+    published_at: String,
+    #[serde(rename = "channelId", default)]
+    channel_id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    thumbnails: Option<Thumbnails>,
+    // the owner of the playlist, the uploader of the video is in videoOwnerChannelTitle
+    #[serde(rename = "channelTitle", default)]
+    channel_title: String,
+    #[serde(rename = "videoOwnerChannelTitle")]
+    video_owner_channel_title: Option<String>,
+    #[serde(rename = "videoOwnerChannelId")]
+    video_owner_channel_id: Option<String>,
+    #[serde(rename = "resourceId")]
+    resource_id: PlaylistResourceId,
+}
+
+#[derive(Deserialize)]
+struct PlaylistResourceId {
+    kind: String,
+    #[serde(rename = "videoId")]
+    video_id: Option<String>,
+}
+
+impl PlaylistItem {
+    fn into_search_item(self) -> SearchItem {
+        let snippet = self.snippet;
+        SearchItem {
+            kind: self.kind,
+            etag: self.etag,
+            id: Id {
+                kind: snippet.resource_id.kind,
+                video_id: snippet.resource_id.video_id,
+                channel_id: None,
+                playlist_id: None,
+            },
+            snippet: Snippet {
+                published_at: snippet.published_at,
+                channel_id: snippet.video_owner_channel_id.unwrap_or(snippet.channel_id),
+                title: snippet.title,
+                description: snippet.description,
+                // deleted and private videos come without thumbnails
+                thumbnails: snippet.thumbnails.unwrap_or_default(),
+                channel_title: snippet
+                    .video_owner_channel_title
+                    .unwrap_or(snippet.channel_title),
+                live_broadcast_content: "none".to_string(),
+            },
+            video_durration: None,
+            url: None,
+        }
+    }
+}
+
+// videos, playlists and channels share this shape, only the id is a plain string
+#[derive(Deserialize)]
+struct ResourceListResponse {
+    #[serde(default)]
+    items: Vec<Resource>,
+}
+
+#[derive(Deserialize)]
+struct Resource {
+    kind: String,
+    #[serde(default)]
+    etag: String,
+    id: String,
+    // missing when only contentDetails was asked for
+    #[serde(default)]
+    snippet: ResourceSnippet,
+    #[serde(rename = "contentDetails")]
+    content_details: Option<ResourceDetails>,
+}
+
+#[derive(Deserialize, Default)]
+struct ResourceSnippet {
+    #[serde(rename = "publishedAt", default)]
+    published_at: String,
+    #[serde(rename = "channelId", default)]
+    channel_id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    thumbnails: Option<Thumbnails>,
+    // channels have no channelTitle, their title is the name
+    #[serde(rename = "channelTitle", default)]
+    channel_title: String,
+    #[serde(rename = "liveBroadcastContent", default)]
+    live_broadcast_content: String,
+}
+
+#[derive(Deserialize)]
+struct ResourceDetails {
+    duration: Option<String>,
+}
+
+impl Resource {
+    fn into_search_item(self) -> SearchItem {
+        let snippet = self.snippet;
+        let mut id = Id {
+            kind: self.kind.clone(),
+            video_id: None,
+            channel_id: None,
+            playlist_id: None,
+        };
+        let mut channel_id = snippet.channel_id;
+        let mut channel_title = snippet.channel_title;
+        match self.kind.as_str() {
+            "youtube#playlist" => id.playlist_id = Some(self.id),
+            "youtube#channel" => {
+                channel_id = self.id.clone();
+                channel_title = snippet.title.clone();
+                id.channel_id = Some(self.id);
+            }
+            _ => id.video_id = Some(self.id),
+        }
+        SearchItem {
+            kind: self.kind,
+            etag: self.etag,
+            id,
+            snippet: Snippet {
+                published_at: snippet.published_at,
+                channel_id,
+                title: snippet.title,
+                description: snippet.description,
+                thumbnails: snippet.thumbnails.unwrap_or_default(),
+                channel_title,
+                live_broadcast_content: snippet.live_broadcast_content,
+            },
+            video_durration: self
+                .content_details
+                .and_then(|details| details.duration)
+                .and_then(|duration| parse_iso8601_duration(&duration)),
+            url: None,
+        }
+    }
+}
+
+// the Data API error body: {"error": {"message": "...", "errors": [{"reason": "quotaExceeded"}]}}
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    error: ApiErrorDetails,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetails {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    errors: Vec<ApiErrorReason>,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorReason {
+    #[serde(default)]
+    reason: String,
+}
+
+fn api_error(status: u16, body: &str) -> AppError {
+    let Ok(body) = serde_json::from_str::<ApiErrorBody>(body) else {
+        return AppError::Http {
+            status,
+            message: body.trim().to_string(),
+        };
+    };
+    let quota_exceeded = body.error.errors.iter().any(|error| {
+        matches!(
+            error.reason.as_str(),
+            "quotaExceeded" | "dailyLimitExceeded"
+        )
+    });
+    if quota_exceeded {
+        AppError::QuotaExceeded
+    } else {
+        AppError::Http {
+            status,
+            message: body.error.message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::UrlTarget;
+    use crate::search::{SearchBackend, YoutubeApiBackend};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const SEARCH_JSON: &str = include_str!("../tests/fixtures/search.json");
+    const VIDEOS_JSON: &str = include_str!("../tests/fixtures/videos.json");
+    const QUOTA_JSON: &str = include_str!("../tests/fixtures/quota_exceeded.json");
+    const CHANNELS_JSON: &str = include_str!("../tests/fixtures/channels.json");
+    const PLAYLIST_ITEMS_JSON: &str = include_str!("../tests/fixtures/playlist_items.json");
+
+    // serves the recorded responses and remembers every request line
+    fn stub_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                reader.read_line(&mut request_line).unwrap();
+                // skip the headers, GET requests have no body
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or("");
+                // a base url ending in /quota answers every call like an exhausted key
+                let (status, body) = if path.starts_with("/quota") {
+                    ("403 Forbidden", QUOTA_JSON)
+                } else if path.starts_with("/search") {
+                    ("200 OK", SEARCH_JSON)
+                } else if path.starts_with("/videos") {
+                    ("200 OK", VIDEOS_JSON)
+                } else if path.starts_with("/channels") {
+                    ("200 OK", CHANNELS_JSON)
+                } else if path.starts_with("/playlistItems") {
+                    ("200 OK", PLAYLIST_ITEMS_JSON)
+                } else {
+                    ("404 Not Found", "{}")
+                };
+                seen.lock().unwrap().push(path.to_string());
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (base_url, requests)
+    }
+
+    #[tokio::test]
+    async fn search_flow_against_stub() {
+        let (base_url, requests) = stub_server();
+        let youtube = YoutubeClient::new(base_url, "test-key", Client::new());
+
+        let data = YoutubeApiBackend::new(youtube)
+            .search(
+                "lofi beats".to_string(),
+                2,
+                Some("CAIQAA".to_string()),
+                SearchFilters::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(data.next_page_token, "CAQQAA");
+        assert_eq!(data.items.len(), 2);
+        assert_eq!(data.items[0].snippet.title, "Lofi Beats to Study");
+        assert_eq!(
+            data.items[0].video_durration,
+            Some(Duration::from_secs(3723))
+        );
+        assert_eq!(data.items[1].video_durration, Some(Duration::from_secs(45)));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("/search?"));
+        assert!(requests[0].contains("q=lofi+beats"));
+        assert!(requests[0].contains("pageToken=CAIQAA"));
+        assert!(requests[0].contains("videoCategoryId=10"));
+        assert!(requests[0].contains("key=test-key"));
+        assert!(requests[1].contains("id=abc123def45%2Cxyz987uvw65"));
+    }
+
+    #[tokio::test]
+    async fn channel_uploads_against_stub() {
+        let (base_url, requests) = stub_server();
+        let youtube = YoutubeClient::new(base_url, "test-key", Client::new());
+
+        let data = YoutubeApiBackend::new(youtube)
+            .channel("UCSJ4gkVC6NrvII8umztf0Ow", 2, None)
+            .await
+            .unwrap();
+
+        assert_eq!(data.next_page_token, "EAAaBlBUOkNBSQ");
+        assert_eq!(data.items.len(), 2);
+        assert_eq!(data.items[0].id.video_id.as_deref(), Some("abc123def45"));
+        assert_eq!(data.items[0].snippet.channel_title, "Lofi Girl");
+        assert_eq!(
+            data.items[0].video_durration,
+            Some(Duration::from_secs(3723))
+        );
+        // a deleted video keeps its row even without thumbnails
+        assert!(data.items[1].snippet.thumbnails.default.is_none());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with("/channels?"));
+        assert!(requests[1].starts_with("/playlistItems?"));
+        assert!(requests[1].contains("playlistId=UUSJ4gkVC6NrvII8umztf0Ow"));
+        assert!(requests[2].starts_with("/videos?"));
+    }
+
+    #[tokio::test]
+    async fn pasted_video_url_is_looked_up() {
+        let (base_url, requests) = stub_server();
+        let youtube = YoutubeClient::new(base_url, "test-key", Client::new());
+        let target = UrlTarget::parse("https://youtu.be/abc123def45?t=42").unwrap();
+
+        let data = YoutubeApiBackend::new(youtube)
+            .lookup(&target)
+            .await
+            .unwrap();
+
+        assert_eq!(data.items.len(), 1);
+        assert_eq!(data.items[0].id.video_id.as_deref(), Some("abc123def45"));
+        assert_eq!(
+            data.items[0].video_durration,
+            Some(Duration::from_secs(3723))
+        );
+        assert!(data.next_page_token.is_empty());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("/videos?part=snippet%2CcontentDetails&id=abc123def45"));
+    }
+
+    #[tokio::test]
+    async fn missing_api_key_is_an_error() {
+        let youtube = YoutubeClient::new("http://127.0.0.1:1", "", Client::new());
+        let error = youtube
+            .search("anything", 5, None, &SearchFilters::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error, AppError::MissingApiKey);
+    }
+
+    #[tokio::test]
+    async fn quota_exceeded_is_detected() {
+        let (base_url, _) = stub_server();
+        let youtube = YoutubeClient::new(format!("{base_url}/quota"), "test-key", Client::new());
+        let error = youtube
+            .search("anything", 5, None, &SearchFilters::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error, AppError::QuotaExceeded);
+    }
+}
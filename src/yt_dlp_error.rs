@@ -0,0 +1,197 @@
+// what went wrong in a failed yt-dlp run, read from its stderr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YtDlpFailure {
+    Unavailable,
+    Private,
+    // age restricted videos and the "confirm you're not a bot" check
+    SignInRequired,
+    GeoBlocked,
+    Throttled,
+    FfmpegMissing,
+    UnsupportedFormat,
+    DiskFull,
+    Other,
+}
+
+impl YtDlpFailure {
+    // the order matters, a private video also says "sign in" and a geo block also says "unavailable"
+    pub fn classify(stderr: &str) -> Self {
+        let stderr = stderr.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|pattern| stderr.contains(pattern));
+
+        if has(&[
+            "no space left on device",
+            "not enough space on the disk",
+            "errno 28",
+        ]) {
+            YtDlpFailure::DiskFull
+        } else if has(&[
+            "ffmpeg not found",
+            "ffprobe not found",
+            "ffmpeg is not installed",
+        ]) {
+            YtDlpFailure::FfmpegMissing
+        } else if has(&["http error 429", "too many requests"]) {
+            YtDlpFailure::Throttled
+        } else if has(&["private video"]) {
+            YtDlpFailure::Private
+        } else if has(&[
+            "not available in your country",
+            "not made this video available in your country",
+            "geo restriction",
+            "geo-restricted",
+        ]) {
+            YtDlpFailure::GeoBlocked
+        } else if has(&[
+            "sign in to confirm",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+            "login required",
+        ]) {
+            YtDlpFailure::SignInRequired
+        } else if has(&["requested format is not available", "unsupported url"]) {
+            YtDlpFailure::UnsupportedFormat
+        } else if has(&[
+            "video unavailable",
+            "this video is not available",
+            "has been removed",
+        ]) {
+            YtDlpFailure::Unavailable
+        } else {
+            YtDlpFailure::Other
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            YtDlpFailure::Unavailable => "video unavailable",
+            YtDlpFailure::Private => "private video",
+            YtDlpFailure::SignInRequired => "sign-in required",
+            YtDlpFailure::GeoBlocked => "blocked in your country",
+            YtDlpFailure::Throttled => "too many requests",
+            YtDlpFailure::FfmpegMissing => "ffmpeg missing",
+            YtDlpFailure::UnsupportedFormat => "format not available",
+            YtDlpFailure::DiskFull => "disk full",
+            YtDlpFailure::Other => "yt-dlp failed",
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            YtDlpFailure::Unavailable => {
+                Some("the video was removed or its channel is gone, there is nothing to download")
+            }
+            YtDlpFailure::Private => Some("only the uploader and invited accounts can watch it"),
+            YtDlpFailure::SignInRequired => {
+                Some("YouTube wants a logged in account, update yt-dlp or try again later")
+            }
+            YtDlpFailure::GeoBlocked => Some("the uploader blocked your country, a VPN can help"),
+            YtDlpFailure::Throttled => Some(
+                "YouTube is rate limiting you, wait a few minutes and use fewer parallel downloads",
+            ),
+            YtDlpFailure::FfmpegMissing => Some(
+                "put ffmpeg and ffprobe in the ffmpeg folder or set their paths in the settings",
+            ),
+            YtDlpFailure::UnsupportedFormat => {
+                Some("pick another format or a lower resolution for this video")
+            }
+            YtDlpFailure::DiskFull => Some("free some space or choose another download folder"),
+            YtDlpFailure::Other => None,
+        }
+    }
+}
+
+// the ERROR: lines of yt-dlp, or the last thing it said if there are none
+pub fn error_message(stderr: &str) -> String {
+    let errors: Vec<&str> = stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("ERROR:"))
+        .map(str::trim)
+        .collect();
+    if !errors.is_empty() {
+        return errors.join("\n");
+    }
+    stderr
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_captured_stderr() {
+        let samples = [
+            (
+                "[youtube] Extracting URL: https://www.youtube.com/watch?v=aaaaaaaaaaa\n\
+                 ERROR: [youtube] aaaaaaaaaaa: Video unavailable. This video has been removed by the uploader",
+                YtDlpFailure::Unavailable,
+            ),
+            (
+                "ERROR: [youtube] aaaaaaaaaaa: Private video. Sign in if you've been granted access to this video",
+                YtDlpFailure::Private,
+            ),
+            (
+                "ERROR: [youtube] aaaaaaaaaaa: Sign in to confirm your age. This video may be inappropriate for some users.",
+                YtDlpFailure::SignInRequired,
+            ),
+            (
+                "ERROR: [youtube] aaaaaaaaaaa: Sign in to confirm you’re not a bot. Use --cookies-from-browser or --cookies for the authentication.",
+                YtDlpFailure::SignInRequired,
+            ),
+            (
+                "ERROR: [youtube] aaaaaaaaaaa: Video unavailable. The uploader has not made this video available in your country",
+                YtDlpFailure::GeoBlocked,
+            ),
+            (
+                "WARNING: [youtube] aaaaaaaaaaa: Retrying (1/3)...\n\
+                 ERROR: unable to download video data: HTTP Error 429: Too Many Requests",
+                YtDlpFailure::Throttled,
+            ),
+            (
+                "ERROR: Postprocessing: ffprobe and ffmpeg not found. Please install or provide the path using --ffmpeg-location",
+                YtDlpFailure::FfmpegMissing,
+            ),
+            (
+                "ERROR: [youtube] aaaaaaaaaaa: Requested format is not available. Use --list-formats for a list of available formats",
+                YtDlpFailure::UnsupportedFormat,
+            ),
+            (
+                "ERROR: unable to write data: [Errno 28] No space left on device",
+                YtDlpFailure::DiskFull,
+            ),
+            (
+                "ERROR: unable to write data: [WinError 112] There is not enough space on the disk",
+                YtDlpFailure::DiskFull,
+            ),
+            (
+                "ERROR: [generic] Unable to download webpage: <urlopen error [Errno -2] Name or service not known>",
+                YtDlpFailure::Other,
+            ),
+        ];
+        for (stderr, expected) in samples {
+            assert_eq!(YtDlpFailure::classify(stderr), expected, "{stderr}");
+        }
+    }
+
+    #[test]
+    fn error_message_prefers_error_lines() {
+        let stderr = "WARNING: [youtube] something odd\n\
+                      ERROR: [youtube] aaaaaaaaaaa: Private video\n";
+        assert_eq!(
+            error_message(stderr),
+            "[youtube] aaaaaaaaaaa: Private video"
+        );
+        assert_eq!(
+            error_message("WARNING: only this\n\n"),
+            "WARNING: only this"
+        );
+        assert_eq!(error_message(""), "");
+    }
+}